@@ -0,0 +1,190 @@
+//! Headless batch runner for automated microprogram testing.
+//!
+//! Drives a `Computer` through a script of simple text directives instead of
+//! the interactive REPL, so microprograms can be graded or exercised in CI
+//! without a terminal attached.
+//!
+//! Supported directives, one per line (blank lines and lines starting with
+//! `#` are ignored):
+//!
+//! - `input FC = 01010101` sets an input register.
+//! - `step` or `step 20` executes one or the given number of instructions.
+//! - `run-until 00011` executes until the given microprogram address is
+//!   reached (or `MAX_RUN_UNTIL_STEPS` is exceeded).
+//! - `expect FE = 00001111` checks an output register or any other bus
+//!   address.
+//! - `expect FLAGS carry=1 zero=0` checks a subset of the alu flags.
+//! - `expect RAM 0x10 = 42` checks a ram cell, given as a hex address and a
+//!   decimal value.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use clap::ArgMatches;
+use regex::Regex;
+
+use emulator::parse::read_program;
+use emulator::{BusAccess, IoRegisters, InterruptLine, Timer};
+
+use super::{Computer, Program};
+
+/// Maximum number of instructions a single `run-until` executes before
+/// giving up in case the target address is never reached.
+const MAX_RUN_UNTIL_STEPS: usize = 1_000_000;
+
+pub fn main(args: &ArgMatches) -> Result<(), i32> {
+    let program_path = Path::new(args.value_of("2i-programm").unwrap());
+    let program_file = File::open(program_path).map_err(|e| {
+        println!("Die angegebene Datei konnte nicht geöffnet werden: {}", e);
+        2
+    })?;
+    let instructions = read_program(program_file).map_err(|e| {
+        println!("Das Mikroprogramm konnte nicht geladen werden: {}", e);
+        3
+    })?;
+    let program = Program { path: program_path.into(), instructions };
+
+    let script_path = Path::new(args.value_of("testdatei").unwrap());
+    let script_file = File::open(script_path).map_err(|e| {
+        println!("Die angegebene Testdatei konnte nicht geöffnet werden: {}", e);
+        4
+    })?;
+
+    let io = IoRegisters::new();
+    let timer = Timer::new(InterruptLine::B);
+    let mut computer = Computer::new(&io, &timer);
+    let patterns = Patterns::new();
+
+    let mut failures = 0;
+    for (number, line) in BufReader::new(script_file).lines().enumerate() {
+        let line = line.map_err(|e| {
+            println!("Die Testdatei konnte nicht gelesen werden: {}", e);
+            4
+        })?;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match run_directive(&mut computer, &io, &program, &patterns, line) {
+            Ok(Some(passed)) => {
+                if passed {
+                    println!("PASS: {}", line);
+                } else {
+                    println!("FAIL: {}", line);
+                    failures += 1;
+                }
+            }
+            Ok(None) => (),
+            Err(err) => {
+                println!("Zeile {}: {}", number + 1, err);
+                return Err(5);
+            }
+        }
+    }
+
+    if failures > 0 {
+        println!("{} von den Erwartungen wurden nicht erfüllt.", failures);
+        Err(6)
+    } else {
+        println!("Alle Erwartungen wurden erfüllt.");
+        Ok(())
+    }
+}
+
+/// The compiled directive patterns, built once up front rather than on
+/// every line.
+struct Patterns {
+    input: Regex,
+    step: Regex,
+    run_until: Regex,
+    expect_bus: Regex,
+    expect_flags: Regex,
+    expect_ram: Regex,
+}
+
+impl Patterns {
+    fn new() -> Patterns {
+        Patterns {
+            input: Regex::new(r"^input\s+(?P<index>F[C-F])\s*=\s*(?P<value>[01]{1,8})$").unwrap(),
+            step: Regex::new(r"^step(?:\s+(?P<count>\d+))?$").unwrap(),
+            run_until: Regex::new(r"^run-until\s+(?P<addr>[01]{1,5})$").unwrap(),
+            expect_bus: Regex::new(r"^expect\s+(?P<index>[0-9A-Fa-f]{2})\s*=\s*(?P<value>[01]{1,8})$").unwrap(),
+            expect_flags: Regex::new(r"^expect\s+FLAGS\s+(?P<flags>.+)$").unwrap(),
+            expect_ram: Regex::new(r"^expect\s+RAM\s+0x(?P<addr>[0-9A-Fa-f]{1,2})\s*=\s*(?P<value>\d{1,3})$").unwrap(),
+        }
+    }
+}
+
+/// Run a single directive, returning the pass/fail result of an `expect`
+/// directive, `None` for directives that only change state, or a
+/// description of why the directive itself couldn't be understood or
+/// executed.
+fn run_directive(computer: &mut Computer<'_>, io: &IoRegisters, program: &Program,
+                  patterns: &Patterns, line: &str) -> Result<Option<bool>, String> {
+    let input_pattern = &patterns.input;
+    let step_pattern = &patterns.step;
+    let run_until_pattern = &patterns.run_until;
+    let expect_bus_pattern = &patterns.expect_bus;
+    let expect_flags_pattern = &patterns.expect_flags;
+    let expect_ram_pattern = &patterns.expect_ram;
+
+    if let Some(captures) = input_pattern.captures(line) {
+        let address = u8::from_str_radix(&captures["index"], 16).unwrap();
+        let value = u8::from_str_radix(&captures["value"], 2).unwrap();
+        io.inspect_input().borrow_mut()[(address - 0xFC) as usize] = value;
+        Ok(None)
+    } else if let Some(captures) = step_pattern.captures(line) {
+        let count = captures.name("count").map_or(1, |m| m.as_str().parse().unwrap());
+        for _ in 0..count {
+            computer.step(program).map_err(|e| format!("Fehler beim Ausführen des Befehls: {}", e))?;
+        }
+        Ok(None)
+    } else if let Some(captures) = run_until_pattern.captures(line) {
+        let addr = usize::from_str_radix(&captures["addr"], 2).unwrap();
+        let mut breakpoints = HashSet::new();
+        breakpoints.insert(addr);
+        computer.run(program, &breakpoints, &[], MAX_RUN_UNTIL_STEPS)
+            .map_err(|e| format!("Fehler beim Ausführen des Befehls: {}", e))?;
+        Ok(None)
+    } else if let Some(captures) = expect_bus_pattern.captures(line) {
+        let address = u8::from_str_radix(&captures["index"], 16).unwrap();
+        let expected = u8::from_str_radix(&captures["value"], 2).unwrap();
+        // FE and FF read back the input registers on the bus, so the output
+        // registers are inspected directly
+        let actual = if address >= 0xFE {
+            io.inspect_output().borrow()[(address - 0xFE) as usize]
+        } else {
+            computer.ram.read(address).map_err(|e| format!("Fehler beim Lesen des Busses: {}", e))?
+        };
+        Ok(Some(actual == expected))
+    } else if let Some(captures) = expect_flags_pattern.captures(line) {
+        let flags = *computer.cpu.inspect_flags();
+        for assertion in captures["flags"].split_whitespace() {
+            let equals = assertion.find('=')
+                .ok_or_else(|| format!("Ungültige Flag-Erwartung: \"{}\"", assertion))?;
+            let (name, value) = (&assertion[..equals], &assertion[equals + 1..]);
+            let expected = value == "1";
+            let actual = match name {
+                "carry" => flags.carry(),
+                "negative" => flags.negative(),
+                "zero" => flags.zero(),
+                _ => return Err(format!("Unbekanntes Flag: \"{}\"", name)),
+            };
+            if actual != expected {
+                return Ok(Some(false));
+            }
+        }
+        Ok(Some(true))
+    } else if let Some(captures) = expect_ram_pattern.captures(line) {
+        let address = u8::from_str_radix(&captures["addr"], 16).unwrap();
+        let expected: u8 = captures["value"].parse().map_err(|_| "Ungültiger RAM-Wert".to_string())?;
+        let actual = computer.ram.inspect().borrow()[address as usize];
+        Ok(Some(actual == expected))
+    } else {
+        Err(format!("Ungültige Testanweisung: \"{}\"", line))
+    }
+}
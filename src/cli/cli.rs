@@ -23,9 +23,15 @@ pub fn build() -> App<'static, 'static> {
                 .help("Das zu konvertierende Mikroprogramm")
                 .required(true)))
         .subcommand(SubCommand::with_name("latex")
-            .about("Erstelle ein LaTeX-Dokument mit einer übersichtlichen Darstellung der gegebenen Programme.")
+            .about("Erstelle eine übersichtliche Darstellung der gegebenen Programme, standardmäßig als LaTeX-Dokument.")
+            .arg(Arg::with_name("format")
+                .help("Zu erstellendes Format")
+                .long("format")
+                .number_of_values(1)
+                .possible_values(&["latex", "markdown", "csv", "json"])
+                .default_value("latex"))
             .arg(Arg::with_name("autor")
-                .help("Autoren der Programme")
+                .help("Autoren der Programme (nur für das LaTeX-Format)")
                 .long("autor")
                 .number_of_values(1)
                 .multiple(true))
@@ -33,6 +39,19 @@ pub fn build() -> App<'static, 'static> {
                 .help("Die darzustellenden Programme")
                 .required(true)
                 .multiple(true)))
+        .subcommand(SubCommand::with_name("debug")
+            .about("Starte den interaktiven Schritt-Debugger mit Breakpoints für ein Mikroprogramm.")
+            .arg(Arg::with_name("2i-programm")
+                .help("Das zu ladende Mikroprogramm")
+                .required(true)))
+        .subcommand(SubCommand::with_name("test")
+            .about("Führe ein Mikroprogramm nicht-interaktiv anhand einer Testdatei aus und prüfe die erwarteten Ergebnisse.")
+            .arg(Arg::with_name("2i-programm")
+                .help("Das zu ladende Mikroprogramm")
+                .required(true))
+            .arg(Arg::with_name("testdatei")
+                .help("Datei mit input/step/run-until/expect-Anweisungen")
+                .required(true)))
 }
 
 pub fn gen_completions(args: &ArgMatches) -> Result<(), i32> {
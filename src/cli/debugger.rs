@@ -0,0 +1,338 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+
+use clap::ArgMatches;
+use rustyline::{CompletionType, Editor};
+
+use emulator::{Instruction, IoRegisters};
+use emulator::parse::read_reachable_program;
+
+use super::{ui, Computer, Program, StopReason, Watchpoint};
+use super::{format_watchpoint, parse_address, parse_watchpoint};
+
+/// Maximum number of instructions "continue" executes before giving up in
+/// case no breakpoint is ever reached (eg. because of an infinite loop).
+const MAX_CONTINUE_STEPS: usize = 1_000_000;
+
+pub fn main(args: &ArgMatches) -> Result<(), i32> {
+    // Load the program from the given path, keeping only reachable
+    // instructions so that unreachable addresses stay at their default NOP
+    let program_path = Path::new(args.value_of("2i-programm").unwrap());
+    let program_file = File::open(program_path).map_err(|e| {
+        println!("Die angegebene Datei konnte nicht geöffnet werden: {}", e);
+        2
+    })?;
+    let reachable = read_reachable_program(&program_file).map_err(|e| {
+        println!("Das Mikroprogramm konnte nicht geladen werden: {}", super::format_error(&e));
+        3
+    })?;
+
+    let mut instructions = [Instruction::default(); 32];
+    for (addr, inst) in reachable {
+        instructions[addr as usize] = inst;
+    }
+    let program = Some(Program { path: program_path.into(), instructions });
+
+    let io = IoRegisters::new();
+    let timer = emulator::Timer::new(emulator::InterruptLine::B);
+    let mut computer = Computer::new(&io, &timer);
+    let mut debugger = Debugger::new();
+
+    println!("Schritt-Debugger für \"{}\". \"help\" für eine Übersicht der Befehle.",
+        program_path.display());
+    ui::status(&mut computer, &io, &program, None);
+
+    let completer = Completer::default();
+    let config = rustyline::Config::builder().completion_type(CompletionType::List);
+    let mut line_reader = Editor::with_config(config.build());
+    line_reader.set_completer(Some(&completer));
+
+    while let Ok(line) = line_reader.readline("debug> ") {
+        let line = line.trim();
+
+        if !line.is_empty() {
+            line_reader.add_history_entry(line);
+        }
+
+        let args: Vec<&str> = line.split_whitespace().collect();
+        if !debugger.run_command(&mut computer, &io, &program, &args) {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Drives a `Computer` over a loaded program one command at a time.
+///
+/// This is the single dispatcher behind the `debug` subcommand's REPL: it
+/// owns the breakpoints, watchpoints and trace-only mode the commands below
+/// operate on, and remembers `last_command` so that an empty command repeats
+/// the previous one, mirroring plain Enter in gdb. Taking already-split
+/// arguments rather than a raw line keeps it usable outside a rustyline loop.
+struct Debugger {
+    breakpoints: HashSet<usize>,
+    watchpoints: Vec<Watchpoint>,
+    trace_only: bool,
+    last_command: Option<Vec<String>>,
+}
+
+impl Debugger {
+    fn new() -> Debugger {
+        Debugger {
+            breakpoints: HashSet::new(),
+            watchpoints: Vec::new(),
+            trace_only: false,
+            last_command: None,
+        }
+    }
+
+    /// Run a single command, given as already-split arguments (eg. `&["s",
+    /// "10"]`), against `computer`. Returns whether the caller should keep
+    /// reading commands (`false` for `quit`/`exit`).
+    ///
+    /// Understands:
+    /// - `s`/`step [n]` - execute the next instruction, or the next `n`.
+    /// - `c`/`continue`/`run` - execute until a breakpoint or watchpoint is
+    ///   hit, or `MAX_CONTINUE_STEPS` is exceeded.
+    /// - `t`/`trace` - toggle trace-only mode.
+    /// - `break <address>` / `clear <address>` - set/remove a breakpoint.
+    /// - `breakpoints` - list the currently set breakpoints.
+    /// - `watch <target>` / `unwatch <target>` - set/remove a watchpoint on a
+    ///   register or RAM/IO cell.
+    /// - `watchpoints` - list the currently set watchpoints.
+    /// - `help` - show the command overview.
+    /// - `quit`/`exit` - stop.
+    ///
+    /// An empty `args` repeats the last command that was run, with whatever
+    /// arguments it used.
+    fn run_command(&mut self, computer: &mut Computer<'_>, io: &IoRegisters,
+                    program: &Option<Program>, args: &[&str]) -> bool {
+        let command: Vec<String> = if args.is_empty() {
+            match self.last_command.clone() {
+                Some(last) => last,
+                None => return true,
+            }
+        } else {
+            args.iter().map(|s| s.to_string()).collect()
+        };
+
+        if command.is_empty() {
+            return true;
+        }
+
+        self.last_command = Some(command.clone());
+        let args: Vec<&str> = command.iter().map(|s| s.as_str()).collect();
+
+        match args[0] {
+            "s" | "step" => {
+                let count = args.get(1).and_then(|n| n.parse().ok()).unwrap_or(1);
+                self.step(computer, io, program, count);
+                true
+            }
+            "c" | "continue" | "run" => {
+                self.run(computer, io, program);
+                true
+            }
+            "t" | "trace" => {
+                self.trace_only = !self.trace_only;
+                println!("Trace-Modus: {}", if self.trace_only { "an" } else { "aus" });
+                true
+            }
+            "break" => {
+                match args.get(1).and_then(|s| parse_address(s)) {
+                    Some(addr) => {
+                        self.breakpoints.insert(addr);
+                        println!("Breakpoint bei {:05b} gesetzt.", addr);
+                    }
+                    None => println!("Ungültige Eingabe. \"help\" für Hilfe."),
+                }
+                true
+            }
+            "clear" => {
+                match args.get(1).and_then(|s| parse_address(s)) {
+                    Some(addr) => {
+                        self.breakpoints.remove(&addr);
+                        println!("Breakpoint bei {:05b} entfernt.", addr);
+                    }
+                    None => println!("Ungültige Eingabe. \"help\" für Hilfe."),
+                }
+                true
+            }
+            "breakpoints" => {
+                if self.breakpoints.is_empty() {
+                    println!("Keine Breakpoints gesetzt.");
+                } else {
+                    let mut addrs: Vec<_> = self.breakpoints.iter().collect();
+                    addrs.sort();
+                    for addr in addrs {
+                        println!("  {:05b}", addr);
+                    }
+                }
+                true
+            }
+            "watch" => {
+                match args.get(1).and_then(|s| parse_watchpoint(s)) {
+                    Some(watchpoint) => {
+                        if !self.watchpoints.contains(&watchpoint) {
+                            self.watchpoints.push(watchpoint);
+                        }
+                        println!("Watchpoint auf {} gesetzt.", format_watchpoint(watchpoint));
+                    }
+                    None => println!("Ungültige Eingabe. \"help\" für Hilfe."),
+                }
+                true
+            }
+            "unwatch" => {
+                match args.get(1).and_then(|s| parse_watchpoint(s)) {
+                    Some(watchpoint) => {
+                        self.watchpoints.retain(|&w| w != watchpoint);
+                        println!("Watchpoint auf {} entfernt.", format_watchpoint(watchpoint));
+                    }
+                    None => println!("Ungültige Eingabe. \"help\" für Hilfe."),
+                }
+                true
+            }
+            "watchpoints" => {
+                if self.watchpoints.is_empty() {
+                    println!("Keine Watchpoints gesetzt.");
+                } else {
+                    for &watchpoint in &self.watchpoints {
+                        println!("  {}", format_watchpoint(watchpoint));
+                    }
+                }
+                true
+            }
+            "help" => {
+                print_help();
+                true
+            }
+            "quit" | "exit" => false,
+            _ => {
+                println!("Ungültige Eingabe. \"help\" für Hilfe.");
+                true
+            }
+        }
+    }
+
+    /// Execute up to `count` instructions, stopping early on error, and print
+    /// the resulting state after each step
+    fn step(&self, computer: &mut Computer<'_>, io: &IoRegisters, program: &Option<Program>,
+            count: usize) {
+        let program_ref = match *program {
+            Some(ref p) => p,
+            None => return,
+        };
+
+        for _ in 0..count {
+            match computer.step(program_ref) {
+                Ok(flags) => {
+                    if self.trace_only {
+                        print_trace_line(computer, program_ref);
+                    } else {
+                        ui::status(computer, io, program, Some(flags));
+                    }
+                }
+                Err(err) => {
+                    println!("Fehler beim Ausführen des Befehls: \"{}\"", err);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Execute instructions until a breakpoint is hit, a watchpoint fires, an
+    /// error occurs or `MAX_CONTINUE_STEPS` is exceeded, printing why
+    /// execution stopped
+    fn run(&self, computer: &mut Computer<'_>, io: &IoRegisters, program: &Option<Program>) {
+        let program_ref = match *program {
+            Some(ref p) => p,
+            None => return,
+        };
+
+        match computer.run(program_ref, &self.breakpoints, &self.watchpoints, MAX_CONTINUE_STEPS) {
+            Ok((StopReason::Breakpoint(addr), flags)) => {
+                println!("Breakpoint bei {:05b} erreicht.", addr);
+                ui::status(computer, io, program, Some(flags));
+            }
+            Ok((StopReason::Watchpoint { watchpoint, old, new }, flags)) => {
+                println!("Watchpoint auf {} ausgelöst: {:08b} -> {:08b}",
+                    format_watchpoint(watchpoint), old, new);
+                ui::status(computer, io, program, Some(flags));
+            }
+            Ok((StopReason::StepBudgetExhausted, _)) => {
+                println!("Maximale Anzahl an Schritten erreicht, ohne einen Breakpoint oder Watchpoint zu treffen.");
+            }
+            Err(err) => {
+                println!("Fehler beim Ausführen des Befehls: \"{}\"", err);
+            }
+        }
+    }
+}
+
+/// Print only the address and decoded mnemonic of the instruction about to
+/// execute, without the full register/flag/bus dump
+fn print_trace_line(computer: &Computer<'_>, program: &Program) {
+    let ip = computer.instruction_pointer;
+    println!("{:05b}: {}", ip, program.instructions[ip].to_mnemonic(Some(ip)));
+}
+
+/// Display a list of all debugger commands with descriptions
+fn print_help() {
+    println!("\n\
+        ENTER             Letzten Befehl wiederholen\n\
+        s                 Nächsten Befehl ausführen\n\
+        s <n>             Die nächsten n Befehle ausführen\n\
+        c/run             Bis zum nächsten Breakpoint oder Watchpoint ausführen\n\
+        t                 Trace-Modus umschalten (nur Adresse + Mnemonic je Schritt)\n\
+        break <adresse>   Breakpoint bei der Adresse setzen (zB: break 00011)\n\
+        clear <adresse>   Breakpoint bei der Adresse entfernen\n\
+        breakpoints       Gesetzte Breakpoints anzeigen\n\
+        watch <ziel>      Watchpoint auf Register (zB: watch R3) oder RAM/IO-Zelle (zB: watch FC) setzen\n\
+        unwatch <ziel>    Watchpoint entfernen\n\
+        watchpoints       Gesetzte Watchpoints anzeigen\n\
+        help              Hilfe anzeigen\n\
+        exit/quit         Debugger beenden\n")
+}
+
+#[derive(Default)]
+struct Completer;
+
+impl rustyline::completion::Completer for Completer {
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        // complete normal commands only at the end
+        if pos < line.len() {
+            return Ok((0, vec![]));
+        }
+
+        let commands = [
+            "s",
+            "c",
+            "continue",
+            "run",
+            "t",
+            "trace",
+            "break ",
+            "clear ",
+            "breakpoints",
+            "watch ",
+            "unwatch ",
+            "watchpoints",
+            "help",
+            "exit",
+            "quit",
+        ];
+
+        let completions = commands.iter().filter_map(|&command| {
+            // Only keep commands, for which the input is a real prefix
+            if command.starts_with(line) && command != line {
+                Some(command.into())
+            } else {
+                None
+            }
+        }).collect();
+
+        Ok((0, completions))
+    }
+}
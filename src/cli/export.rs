@@ -0,0 +1,324 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ArgMatches;
+use emulator::Instruction;
+use emulator::parse::read_reachable_program;
+
+static TEMPLATE: &'static str = include_str!("latex.tex");
+
+/// A backend that turns decoded microprograms into some output format.
+///
+/// `main` calls `header` once, then `program` once per input file (in the
+/// order given on the command line) and finally `footer` once, all writing
+/// to the same sink.
+trait Exporter {
+    fn header(&self, out: &mut dyn Write, author: &str) -> io::Result<()>;
+    fn program(&self, out: &mut dyn Write, filename: &str, program: &[(u8, Instruction)]) -> io::Result<()>;
+    fn footer(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+pub fn main(args: &ArgMatches<'_>) -> Result<(), i32> {
+    // Load programs eagerly and remember their paths
+    let programs = args.values_of("2i-programm").unwrap().map(|arg| {
+        let program_path = Path::new(arg);
+        let program_file = File::open(program_path).map_err(|e| {
+            eprintln!("Die angegebene Datei konnte nicht geöffnet werden: {}", e);
+            2
+        })?;
+        let program = read_reachable_program(&program_file).map_err(|e| {
+            eprintln!("Das Mikroprogramm konnte nicht geladen werden: {}", super::format_error(&e));
+            3
+        })?;
+
+        Ok((program_path.to_owned(), program))
+    }).collect::<Result<Vec<_>,i32>>()?;
+
+    let author = args.values_of("autor")
+        .map(|authors| authors.collect::<Vec<_>>().join(", "))
+        .unwrap_or_default();
+
+    let exporter: Box<dyn Exporter> = match args.value_of("format").unwrap_or("latex") {
+        "latex" => Box::new(LatexExporter::new()),
+        "markdown" => Box::new(MarkdownExporter),
+        "csv" => Box::new(CsvExporter),
+        "json" => Box::new(JsonExporter::new()),
+        format => {
+            eprintln!("Unbekanntes Format: {}", format);
+            return Err(2);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    exporter.header(&mut out, &author).map_err(|_| 4)?;
+    for (path, program) in programs {
+        exporter.program(&mut out, &path.to_string_lossy(), &program).map_err(|_| 4)?;
+    }
+    exporter.footer(&mut out).map_err(|_| 4)?;
+
+    Ok(())
+}
+
+/// The original backend, emitting a print-ready LaTeX document built around
+/// `latex.tex`, with its `\verb|...|` table rows paginated across multiple
+/// pages.
+struct LatexExporter {
+    header_template: &'static str,
+    page_separator: &'static str,
+    footer_template: &'static str,
+    // The first page can only contain 37 lines because of the header
+    lines_remaining: Cell<usize>,
+}
+
+impl LatexExporter {
+    fn new() -> LatexExporter {
+        let mut template = TEMPLATE.split("#split#");
+        LatexExporter {
+            header_template: template.next().unwrap(),
+            page_separator: template.next().unwrap(),
+            footer_template: template.next().unwrap(),
+            lines_remaining: Cell::new(37),
+        }
+    }
+}
+
+impl Exporter for LatexExporter {
+    fn header(&self, out: &mut dyn Write, author: &str) -> io::Result<()> {
+        let author = if author.is_empty() {
+            Cow::Borrowed("")
+        } else {
+            Cow::Owned("\\indent -- ".to_owned() + author)
+        };
+
+        write!(out, "{}", self.header_template.replace("#author#", &author))
+    }
+
+    fn program(&self, out: &mut dyn Write, filename: &str, program: &[(u8, Instruction)]) -> io::Result<()> {
+        // 2 lines are used for the program header and some margin
+        if self.lines_remaining.get() < program.len() + 2 {
+            // Start new program table on new page (works because programs
+            // cannot be longer than 32 + 2 lines)
+            write!(out, "{}", self.page_separator)?;
+            self.lines_remaining.set(40);
+        }
+        self.lines_remaining.set(self.lines_remaining.get() - (program.len() + 2));
+
+        writeln!(out)?;
+        writeln!(out, "    % Generated from {}", filename)?;
+        writeln!(out, "    \\multicolumn{{15}}{{l}}{{}}\\\\\\multicolumn{{15}}{{l}}{{\\textbf{{{}}}}}\\\\\\hline", escape_latex(filename))?;
+
+        for &(addr, inst) in program.iter() {
+            writeln!(out, "    {}&\\verb|{}|&{:05b}&{:02b}&{:05b}&{:01b}&{:01b}&{:03b}&{:04b}&{:01b}&{:01b}&{:01b}&{:01b}&{:04b}&{:01b}\\\\\\hline",
+                addr,
+                inst.to_mnemonic(Some(addr as usize)),
+                addr,
+                inst.get_address_control(),
+                inst.get_next_instruction_address(),
+                inst.is_bus_writable() as u8,
+                inst.is_bus_enabled() as u8,
+                inst.get_register_address_a(),
+                inst.get_constant_input() & 0b1111,
+                inst.should_write_register_b() as u8,
+                inst.should_write_register() as u8,
+                inst.is_alu_input_a_bus() as u8,
+                inst.is_alu_input_b_const() as u8,
+                inst.get_alu_instruction(),
+                inst.should_store_flags() as u8)?;
+        }
+
+        Ok(())
+    }
+
+    fn footer(&self, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "{}", self.footer_template)
+    }
+}
+
+fn escape_latex(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+
+    for char in string.chars() {
+        match char {
+            '&' => result.push_str("\\&"),
+            '%' => result.push_str("\\%"),
+            '$' => result.push_str("\\$"),
+            '#' => result.push_str("\\#"),
+            '_' => result.push_str("\\_"),
+            '{' => result.push_str("\\{"),
+            '}' => result.push_str("\\}"),
+            '~' => result.push_str("\\textasciitilde{}"),
+            '^' => result.push_str("\\textasciicircum{}"),
+            '\\' => result.push_str("\\textbackslash{}"),
+            _ => result.push(char),
+        }
+    }
+
+    result
+}
+
+/// Emits one Markdown table per program, for pasting into documentation.
+struct MarkdownExporter;
+
+impl Exporter for MarkdownExporter {
+    fn header(&self, _out: &mut dyn Write, _author: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn program(&self, out: &mut dyn Write, filename: &str, program: &[(u8, Instruction)]) -> io::Result<()> {
+        writeln!(out, "## {}", filename)?;
+        writeln!(out)?;
+        writeln!(out, "| Adr | Mnemonic | MAC | NA | SB | BS | MRA | MRB/C | MWW | MWR | MAB | MCB | ALU | ST |")?;
+        writeln!(out, "|---|---|---|---|---|---|---|---|---|---|---|---|---|---|")?;
+
+        for &(addr, inst) in program.iter() {
+            writeln!(out, "| {} | `{}` | {:02b} | {:05b} | {} | {} | {:03b} | {:04b} | {} | {} | {} | {} | {:04b} | {} |",
+                addr,
+                inst.to_mnemonic(Some(addr as usize)),
+                inst.get_address_control(),
+                inst.get_next_instruction_address(),
+                inst.is_bus_writable() as u8,
+                inst.is_bus_enabled() as u8,
+                inst.get_register_address_a(),
+                inst.get_constant_input() & 0b1111,
+                inst.should_write_register_b() as u8,
+                inst.should_write_register() as u8,
+                inst.is_alu_input_a_bus() as u8,
+                inst.is_alu_input_b_const() as u8,
+                inst.get_alu_instruction(),
+                inst.should_store_flags() as u8)?;
+        }
+
+        writeln!(out)
+    }
+
+    fn footer(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Emits a single CSV table (one row per instruction, across all programs)
+/// with the source file name as an extra column, for pasting into a
+/// spreadsheet.
+struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn header(&self, out: &mut dyn Write, _author: &str) -> io::Result<()> {
+        writeln!(out, "Datei,Adr,Mnemonic,MAC,NA,SB,BS,MRA,MRB/C,MWW,MWR,MAB,MCB,ALU,ST")
+    }
+
+    fn program(&self, out: &mut dyn Write, filename: &str, program: &[(u8, Instruction)]) -> io::Result<()> {
+        for &(addr, inst) in program.iter() {
+            writeln!(out, "{},{},\"{}\",{:02b},{:05b},{},{},{:03b},{:04b},{},{},{},{},{:04b},{}",
+                escape_csv(filename),
+                addr,
+                inst.to_mnemonic(Some(addr as usize)),
+                inst.get_address_control(),
+                inst.get_next_instruction_address(),
+                inst.is_bus_writable() as u8,
+                inst.is_bus_enabled() as u8,
+                inst.get_register_address_a(),
+                inst.get_constant_input() & 0b1111,
+                inst.should_write_register_b() as u8,
+                inst.should_write_register() as u8,
+                inst.is_alu_input_a_bus() as u8,
+                inst.is_alu_input_b_const() as u8,
+                inst.get_alu_instruction(),
+                inst.should_store_flags() as u8)?;
+        }
+
+        Ok(())
+    }
+
+    fn footer(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn escape_csv(string: &str) -> String {
+    string.replace('"', "\"\"")
+}
+
+/// Emits a single JSON array of `{"file": ..., "instructions": [...]}`
+/// objects, one per program, for feeding disassembled microprograms into
+/// other tooling.
+struct JsonExporter {
+    first_program: Cell<bool>,
+}
+
+impl JsonExporter {
+    fn new() -> JsonExporter {
+        JsonExporter { first_program: Cell::new(true) }
+    }
+}
+
+impl Exporter for JsonExporter {
+    fn header(&self, out: &mut dyn Write, _author: &str) -> io::Result<()> {
+        writeln!(out, "[")
+    }
+
+    fn program(&self, out: &mut dyn Write, filename: &str, program: &[(u8, Instruction)]) -> io::Result<()> {
+        if ! self.first_program.get() {
+            writeln!(out, ",")?;
+        }
+        self.first_program.set(false);
+
+        writeln!(out, "  {{")?;
+        writeln!(out, "    \"file\": \"{}\",", escape_json(filename))?;
+        writeln!(out, "    \"instructions\": [")?;
+
+        for (i, &(addr, inst)) in program.iter().enumerate() {
+            write!(out, "      {{\"address\": {}, \"mnemonic\": \"{}\", \"address_control\": {}, \
+                \"next_instruction_address\": {}, \"bus_writable\": {}, \"bus_enabled\": {}, \
+                \"register_address_a\": {}, \"constant_input\": {}, \"write_register_b\": {}, \
+                \"write_register\": {}, \"alu_input_a_bus\": {}, \"alu_input_b_const\": {}, \
+                \"alu_instruction\": {}, \"store_flags\": {}}}",
+                addr,
+                escape_json(&inst.to_mnemonic(Some(addr as usize))),
+                inst.get_address_control(),
+                inst.get_next_instruction_address(),
+                inst.is_bus_writable(),
+                inst.is_bus_enabled(),
+                inst.get_register_address_a(),
+                inst.get_constant_input() & 0b1111,
+                inst.should_write_register_b(),
+                inst.should_write_register(),
+                inst.is_alu_input_a_bus(),
+                inst.is_alu_input_b_const(),
+                inst.get_alu_instruction(),
+                inst.should_store_flags())?;
+
+            if i + 1 < program.len() {
+                writeln!(out, ",")?;
+            } else {
+                writeln!(out)?;
+            }
+        }
+
+        write!(out, "    ]\n  }}")
+    }
+
+    fn footer(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(out, "]")
+    }
+}
+
+fn escape_json(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+
+    for char in string.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            _ => result.push(char),
+        }
+    }
+
+    result
+}
@@ -13,7 +13,7 @@ pub fn main(args: &ArgMatches) -> Result<(), i32> {
         2
     })?;
     let program = read_reachable_program(&program_file).map_err(|e| {
-        println!("Das Mikroprogramm konnte nicht geladen werden: {}", e);
+        println!("Das Mikroprogramm konnte nicht geladen werden: {}", super::format_error(&e));
         3
     })?;
 
@@ -1,15 +1,28 @@
+mod batch;
 mod cli;
+mod debugger;
+mod export;
 mod ipg;
-mod latex;
 mod ui;
 
+use std::collections::HashSet;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
+use emulator::BusAccess;
 use regex::Regex;
 use rustyline::{CompletionType, Editor};
 
+/// Maximum number of instructions a bare "run" executes before giving up in
+/// case no breakpoint or watchpoint is ever reached (eg. because of an
+/// infinite loop).
+const MAX_RUN_STEPS: usize = 1_000_000;
+
 fn main() {
+    // Installs a logger reading its level from `RUST_LOG`, so `RUST_LOG=trace`
+    // captures a full execution transcript from the emulator's cpu tracing
+    env_logger::init();
+
     if let Err(e) = _main() {
         std::process::exit(e);
     }
@@ -22,7 +35,9 @@ fn _main() -> Result<(), i32> {
     match args.subcommand() {
         ("completions", Some(args)) => return cli::gen_completions(args),
         ("ipg-csv", Some(args)) => return ipg::main(args),
-        ("latex", Some(args)) => return latex::main(args),
+        ("latex", Some(args)) => return export::main(args),
+        ("debug", Some(args)) => return debugger::main(args),
+        ("test", Some(args)) => return batch::main(args),
         _ => (),
     }
 
@@ -34,8 +49,13 @@ fn _main() -> Result<(), i32> {
     };
 
     let io = emulator::IoRegisters::new();
-    let mut computer = Computer::new(&io);
+    let timer = emulator::Timer::new(emulator::InterruptLine::B);
+    let mut computer = Computer::new(&io, &timer);
     let mut last_file = String::from("none");
+    let mut last_file_is_assembly = false;
+    let mut breakpoints: HashSet<usize> = HashSet::new();
+    let mut watchpoints: Vec<Watchpoint> = Vec::new();
+    let mut trace_only = false;
 
     println!("2i-emulator {}, GPLv3, https://github.com/klemens/2i-emulator",
              option_env!("CARGO_PKG_VERSION").unwrap_or("*"));
@@ -63,7 +83,11 @@ fn _main() -> Result<(), i32> {
                 // Execute next instruction and display the updated ui
                 match computer.step(&program_inner) {
                     Ok(flags) => {
-                        ui::status(&mut computer, &io, &program, Some(flags));
+                        if trace_only {
+                            print_trace_line(&computer, program_inner);
+                        } else {
+                            ui::status(&mut computer, &io, &program, Some(flags));
+                        }
                     }
                     Err(err) => {
                         println!("Fehler beim Ausführen des Befehls: \"{}\"", err);
@@ -79,8 +103,20 @@ fn _main() -> Result<(), i32> {
             if let Ok(prog) = load_programm(Path::new(&main_path)) {
                 program = Some(prog);
                 last_file = main_path;
+                last_file_is_assembly = false;
+                // Reset computer (only keep io registers)
+                computer = Computer::new(&io, &timer);
+                ui::status(&mut computer, &io, &program, None);
+            }
+        } else if line.starts_with("assemble ") {
+            let main_path = cmdline_parser::parse_single(&line[9..].trim());
+
+            if let Ok(prog) = assemble_programm(Path::new(&main_path)) {
+                program = Some(prog);
+                last_file = main_path;
+                last_file_is_assembly = true;
                 // Reset computer (only keep io registers)
-                computer = Computer::new(&io);
+                computer = Computer::new(&io, &timer);
                 ui::status(&mut computer, &io, &program, None);
             }
         } else if line.starts_with("trigger ") {
@@ -93,15 +129,61 @@ fn _main() -> Result<(), i32> {
                 }
             };
             ui::status(&mut computer, &io, &program, None);
+        } else if line == "run" {
+            run(&mut computer, &io, &program, &breakpoints, &watchpoints, MAX_RUN_STEPS);
+        } else if let Some(count) = line.strip_prefix("run ").and_then(|n| n.trim().parse().ok()) {
+            run(&mut computer, &io, &program, &breakpoints, &watchpoints, count);
+        } else if line == "trace on" || line == "trace off" {
+            trace_only = line == "trace on";
+            println!("Trace-Modus: {}", if trace_only { "an" } else { "aus" });
+        } else if let Some(addr) = line.strip_prefix("break ").and_then(parse_address) {
+            breakpoints.insert(addr);
+            println!("Breakpoint bei {:05b} gesetzt.", addr);
+        } else if let Some(addr) = line.strip_prefix("unbreak ").and_then(parse_address) {
+            breakpoints.remove(&addr);
+            println!("Breakpoint bei {:05b} entfernt.", addr);
+        } else if line == "breakpoints" {
+            if breakpoints.is_empty() {
+                println!("Keine Breakpoints gesetzt.");
+            } else {
+                let mut addrs: Vec<_> = breakpoints.iter().collect();
+                addrs.sort();
+                for addr in addrs {
+                    println!("  {:05b}", addr);
+                }
+            }
+        } else if let Some(watchpoint) = line.strip_prefix("watch ").and_then(parse_watchpoint) {
+            if ! watchpoints.contains(&watchpoint) {
+                watchpoints.push(watchpoint);
+            }
+            println!("Watchpoint auf {} gesetzt.", format_watchpoint(watchpoint));
+        } else if let Some(watchpoint) = line.strip_prefix("unwatch ").and_then(parse_watchpoint) {
+            watchpoints.retain(|&w| w != watchpoint);
+            println!("Watchpoint auf {} entfernt.", format_watchpoint(watchpoint));
+        } else if line == "watchpoints" {
+            if watchpoints.is_empty() {
+                println!("Keine Watchpoints gesetzt.");
+            } else {
+                for &watchpoint in &watchpoints {
+                    println!("  {}", format_watchpoint(watchpoint));
+                }
+            }
         } else if line == "exit" || line == "quit" {
             break;
         } else if line == "reload" {
-            if last_file.eq("none") {
+            let reloaded = if last_file.eq("none") {
                 println!("Es wurde noch keine Datei geladen");
-            } else if let Ok(prog) = load_programm(Path::new(&last_file))  {    
+                None
+            } else if last_file_is_assembly {
+                assemble_programm(Path::new(&last_file)).ok()
+            } else {
+                load_programm(Path::new(&last_file)).ok()
+            };
+
+            if let Some(prog) = reloaded {
                 program = Some(prog);
                 // Reset computer (only keep io registers)
-                computer = Computer::new(&io);
+                computer = Computer::new(&io, &timer);
                 ui::status(&mut computer, &io, &program, None);
             }
         } else if line == "help" {
@@ -139,13 +221,23 @@ fn _main() -> Result<(), i32> {
     Ok(())
 }
 
+/// Format an `emulator::Error` the way the CLI presents it to the user: a
+/// `Diagnostic`'s precise line/column is rendered in German via `render_de`,
+/// every other variant falls back to its plain `Display`.
+pub(crate) fn format_error(err: &emulator::Error) -> String {
+    match err {
+        &emulator::Error::Diagnostic(ref diagnostic) => diagnostic.render_de(),
+        err => err.to_string(),
+    }
+}
+
 /// Load 2i program from path and print errors to stdout if it failes
 fn load_programm(path: &Path) -> Result<Program, ()> {
     if let Ok(file) = File::open(&path) {
         match emulator::parse::read_program(file) {
             Ok(program) => Ok(Program { path: path.into(), instructions: program }),
             Err(err) => {
-                println!("Fehler beim Laden des Programms: {}", err);
+                println!("Fehler beim Laden des Programms: {}", format_error(&err));
                 Err(())
             }
         }
@@ -155,28 +247,168 @@ fn load_programm(path: &Path) -> Result<Program, ()> {
     }
 }
 
+/// Assemble a 2i program written in mnemonic syntax from path and print
+/// errors to stdout if it failes
+fn assemble_programm(path: &Path) -> Result<Program, ()> {
+    if let Ok(file) = File::open(&path) {
+        match emulator::parse::assemble_program(file) {
+            Ok(program) => Ok(Program { path: path.into(), instructions: program }),
+            Err(err) => {
+                println!("Fehler beim Assemblieren des Programms: {}", format_error(&err));
+                Err(())
+            }
+        }
+    } else {
+        println!("Die angegebene Datei konnte nicht geöffnet werden.");
+        Err(())
+    }
+}
+
+/// Execute instructions until a breakpoint is hit, a watchpoint fires, an
+/// error occurs or `max_steps` is exceeded, printing why execution stopped
+fn run(computer: &mut Computer<'_>, io: &emulator::IoRegisters, program: &Option<Program>,
+       breakpoints: &HashSet<usize>, watchpoints: &[Watchpoint], max_steps: usize) {
+    let program_ref = match *program {
+        Some(ref p) => p,
+        None => return,
+    };
+
+    match computer.run(program_ref, breakpoints, watchpoints, max_steps) {
+        Ok((StopReason::Breakpoint(addr), flags)) => {
+            println!("Breakpoint bei {:05b} erreicht.", addr);
+            ui::status(computer, io, program, Some(flags));
+        }
+        Ok((StopReason::Watchpoint { watchpoint, old, new }, flags)) => {
+            println!("Watchpoint auf {} ausgelöst: {:08b} -> {:08b}",
+                format_watchpoint(watchpoint), old, new);
+            ui::status(computer, io, program, Some(flags));
+        }
+        Ok((StopReason::StepBudgetExhausted, _)) => {
+            println!("Maximale Anzahl an Schritten erreicht, ohne einen Breakpoint oder Watchpoint zu treffen.");
+        }
+        Err(err) => {
+            println!("Fehler beim Ausführen des Befehls: \"{}\"", err);
+        }
+    }
+}
+
+/// Print only the address and decoded mnemonic of the instruction about to
+/// execute, without the full register/flag/bus dump
+fn print_trace_line(computer: &Computer<'_>, program: &Program) {
+    let ip = computer.instruction_pointer;
+    println!("{:05b}: {}", ip, program.instructions[ip].to_mnemonic(Some(ip)));
+}
+
+/// Parse a 5 bit binary instruction address, as used by `break`/`unbreak`
+pub(crate) fn parse_address(s: &str) -> Option<usize> {
+    usize::from_str_radix(s.trim(), 2).ok().filter(|&addr| addr < 32)
+}
+
+/// Parse a watchpoint target, either a register (`R0` to `R7`) or a memory/IO
+/// cell addressed by a 2 digit hex bus address (eg: `watch FC`)
+pub(crate) fn parse_watchpoint(s: &str) -> Option<Watchpoint> {
+    let s = s.trim();
+
+    if let Some(register) = s.strip_prefix('R').or_else(|| s.strip_prefix('r')) {
+        register.parse().ok().filter(|&r| r < 8).map(Watchpoint::Register)
+    } else {
+        u8::from_str_radix(s, 16).ok().map(Watchpoint::Memory)
+    }
+}
+
+/// Format a watchpoint the same way `parse_watchpoint` accepts it
+pub(crate) fn format_watchpoint(watchpoint: Watchpoint) -> String {
+    match watchpoint {
+        Watchpoint::Register(r) => format!("R{}", r),
+        Watchpoint::Memory(addr) => format!("{:02X}", addr),
+    }
+}
+
 #[derive(Default)]
 pub struct Computer<'a> {
     cpu: emulator::Cpu,
     instruction_pointer: usize,
     ram: emulator::Ram<'a>,
+    timers: Vec<&'a emulator::Timer>,
 }
 
 impl<'a> Computer<'a> {
-    fn new(io: &'a emulator::IoRegisters) -> Computer<'a> {
+    /// Create a computer with `io` mapped at FC-FF and `timer` mapped at its
+    /// reload/status registers at FA-FB, giving microprograms a background
+    /// time source to poll or to receive interrupts from.
+    fn new(io: &'a emulator::IoRegisters, timer: &'a emulator::Timer) -> Computer<'a> {
         let mut computer = Computer::default();
         computer.ram.add_overlay(0xFC, 0xFF, io);
+        computer.attach_timer(0xFA, timer);
         computer
     }
 
-    /// Execute next instruction and update the instruction pointer
+    /// Map a `Timer` into the reload/status registers at `address` and
+    /// `address + 1` and have `step` advance it once per executed
+    /// instruction, so it can raise its interrupt line in the background.
+    fn attach_timer(&mut self, address: u8, timer: &'a emulator::Timer) {
+        self.ram.add_overlay(address, address + 1, timer);
+        self.timers.push(timer);
+    }
+
+    /// Execute next instruction, advance any attached timers and update the
+    /// instruction pointer
     fn step(&mut self, program: &Program) -> emulator::Result<emulator::Flags> {
         let instruction = program.instructions[self.instruction_pointer];
-        self.cpu.execute_instruction(instruction, &mut self.ram).map(|(ip, flags)| {
+        let result = self.cpu.execute_instruction(instruction, &mut self.ram).map(|(ip, flags)| {
             self.instruction_pointer = ip;
             flags
+        });
+
+        if result.is_ok() {
+            for timer in &self.timers {
+                timer.tick(self.cpu.inspect_interrupts());
+            }
+        }
+
+        result
+    }
+
+    /// Read the current value observed by a watchpoint.
+    fn watchpoint_value(&mut self, watchpoint: Watchpoint) -> emulator::Result<u8> {
+        Ok(match watchpoint {
+            Watchpoint::Register(r) => self.cpu.inspect_registers()[r],
+            Watchpoint::Memory(addr) => self.ram.read(addr)?,
         })
     }
+
+    /// Execute instructions until a microprogram-address breakpoint is hit,
+    /// a watched register or memory/IO cell changes value, or `max_steps`
+    /// instructions have run without either happening.
+    ///
+    /// Returns the reason execution stopped together with the flags of the
+    /// last executed instruction (the default flags if no instruction ran).
+    pub fn run(&mut self, program: &Program, breakpoints: &HashSet<usize>,
+               watchpoints: &[Watchpoint], max_steps: usize) -> emulator::Result<(StopReason, emulator::Flags)> {
+        let mut last_values = Vec::with_capacity(watchpoints.len());
+        for &watchpoint in watchpoints {
+            last_values.push(self.watchpoint_value(watchpoint)?);
+        }
+
+        let mut flags = emulator::Flags::default();
+        for _ in 0..max_steps {
+            flags = self.step(program)?;
+
+            if breakpoints.contains(&self.instruction_pointer) {
+                return Ok((StopReason::Breakpoint(self.instruction_pointer), flags));
+            }
+
+            for (&watchpoint, last) in watchpoints.iter().zip(last_values.iter_mut()) {
+                let current = self.watchpoint_value(watchpoint)?;
+                if current != *last {
+                    return Ok((StopReason::Watchpoint { watchpoint, old: *last, new: current }, flags));
+                }
+                *last = current;
+            }
+        }
+
+        Ok((StopReason::StepBudgetExhausted, flags))
+    }
 }
 
 pub struct Program {
@@ -184,6 +416,26 @@ pub struct Program {
     instructions: [emulator::Instruction; 32],
 }
 
+/// A named location a watchpoint can observe: either a register (`R0` to
+/// `R7`) or a memory/IO cell addressed by its 8 bit bus address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watchpoint {
+    Register(usize),
+    Memory(u8),
+}
+
+/// Why `Computer::run` stopped.
+#[derive(Debug, Clone, Copy)]
+pub enum StopReason {
+    /// A microprogram-address breakpoint was hit.
+    Breakpoint(usize),
+    /// The given watchpoint's value changed from `old` to `new`.
+    Watchpoint { watchpoint: Watchpoint, old: u8, new: u8 },
+    /// `max_steps` instructions were executed without hitting a breakpoint
+    /// or watchpoint.
+    StepBudgetExhausted,
+}
+
 #[derive(Default)]
 struct Completer {
     path_completer: rustyline::completion::FilenameCompleter,
@@ -191,8 +443,8 @@ struct Completer {
 
 impl rustyline::completion::Completer for Completer {
     fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
-        // complete file paths for the load command
-        if line.starts_with("load ") && pos >= 5 {
+        // complete file paths for the load/assemble commands
+        if (line.starts_with("load ") && pos >= 5) || (line.starts_with("assemble ") && pos >= 9) {
             return self.path_completer.complete(line, pos);
         }
 
@@ -204,6 +456,7 @@ impl rustyline::completion::Completer for Completer {
         let commands = [
             "exit",
             "load ",
+            "assemble ",
             "reload",
             "FC = ",
             "FD = ",
@@ -211,6 +464,15 @@ impl rustyline::completion::Completer for Completer {
             "FF = ",
             "trigger INTA",
             "trigger INTB",
+            "run",
+            "trace on",
+            "trace off",
+            "break ",
+            "unbreak ",
+            "breakpoints",
+            "watch ",
+            "unwatch ",
+            "watchpoints",
             "help",
             "quit",
             "clear",
@@ -117,9 +117,23 @@ pub fn display_help() {
         FX = <value>  Eingaberegister setzen (zB: FC = 11010)\n\
         ENTER         Nächsten Befehl ausführen\n\
         load <path>   Neues Mikroprogramm laden (CPU wird zurückgesetzt)\n\
+        assemble <path>\
+      \n              Mikroprogramm im Mnemonic-Format assemblieren und laden (CPU wird zurückgesetzt)\n\
         trigger <int> Interrupt auslösen:\
       \n                INTA (MAC 010): Nur für den nächsten Befehl gültig\
       \n                INTB (MAC 111): Gültig bis zum nächsten Befehl mit MAC = 111\n\
+        (FA/FB fest verdrahteter Timer: FA = Reload, FB = Status, löst MAC 111 aus)\n\
+        run [n]       Bis zum nächsten Breakpoint oder Watchpoint ausführen (optional nach spätestens n Schritten abbrechen)\n\
+        trace on/off  Trace-Modus umschalten (nur Adresse + Mnemonic je Schritt)\n\
+        break <adresse>\
+      \n              Breakpoint bei der Adresse setzen (zB: break 00011)\n\
+        unbreak <adresse>\
+      \n              Breakpoint bei der Adresse entfernen\n\
+        breakpoints   Gesetzte Breakpoints anzeigen\n\
+        watch <ziel>  Watchpoint auf Register (zB: watch R3) oder RAM/IO-Zelle (zB: watch FC) setzen\n\
+        unwatch <ziel>\
+      \n              Watchpoint entfernen\n\
+        watchpoints   Gesetzte Watchpoints anzeigen\n\
         ram           RAM-Übersicht anzeigen\n\
         program       Mikroprogramm anzeigen (ohne NOPs)\n\
         help          Hilfe anzeigen\n\
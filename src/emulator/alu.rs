@@ -2,51 +2,83 @@
 //!
 //! This module contains the alu used in the 2i.
 
+use super::Result;
+use super::variant::Variant;
+
 /// Alu of the 2i.
 pub struct Alu;
 
 impl Alu {
-    /// Execute an instruction with two operands on the alu.
+    /// Execute an instruction with two operands on the alu, using the
+    /// opcode table of the given `Variant`.
+    ///
+    /// Returns the result and the resulting flags, or an `Error::Cpu` if
+    /// `V` doesn't implement this opcode. Opcodes higher than 1111 == 15
+    /// are not a valid 4 bit field and still result in a panic.
+    #[cfg(not(feature = "decimal_mode"))]
+    pub fn calculate<V: Variant>(instruction: u8, a: u8, b: u8, carry: bool) -> Result<(u8, Flags)> {
+        V::alu_calculate(instruction, a, b, carry)
+    }
+
+    /// Execute an instruction with two operands on the alu, using the
+    /// opcode table of the given `Variant`.
     ///
-    /// Returns the result and the resulting flags. Higher instructions than
-    /// 1111 == 15 will result in a panic.
-    pub fn calculate(instruction: u8, a: u8, b: u8, carry: bool) -> (u8, Flags) {
-        let (result, carry) = match instruction {
-            0b0000 => (a, false),
-            0b0001 => (b, false),
-            0b0010 => (!(a | b), false),
-            0b0011 => (0, false),
-            0b0100 => a.overflowing_add(b),
-            0b0101 => { // inverted carry
-                let tmp1 = a.overflowing_add(b);
-                let tmp2 = tmp1.0.overflowing_add(1);
-                (tmp2.0, !(tmp1.1 | tmp2.1))
-            }
-            0b0110 => {
-                let tmp1 = a.overflowing_add(b);
-                let tmp2 = tmp1.0.overflowing_add(if carry {1} else {0});
-                (tmp2.0, tmp1.1 | tmp2.1)
-            }
-            0b0111 => { // inverted carry
-                let tmp1 = a.overflowing_add(b);
-                let tmp2 = tmp1.0.overflowing_add(if carry {0} else {1});
-                (tmp2.0, !(tmp1.1 | tmp2.1))
-            }
-            0b1000 => (a >> 1, a & 0b00000001 != 0),
-            0b1001 => (a.rotate_right(1), a & 0b00000001 != 0),
-            0b1010 => (a >> 1 | (carry as u8) << 7, a & 0b00000001 != 0),
-            0b1011 => (a >> 1 | (a & 0b10000000), a & 0b00000001 != 0),
-            0b1100 => (0, false),
-            0b1101 => (0, true),
-            0b1110 => (0, carry),
-            0b1111 => (0, !carry),
-            _ => panic!("Invalid alu instruction {}", instruction),
+    /// The 2i's ALU opcode is only 4 bits wide and every one of its 16
+    /// values is already assigned by the real hardware, so this feature
+    /// can't add a new opcode without changing the instruction format.
+    /// Instead it reuses `DECIMAL_ADD_OPCODE` (`0b0011`, which every
+    /// `Variant` maps to the rarely useful "always return 0") as the
+    /// decimal-add selector, so `calculate_decimal` is reachable from a
+    /// microprogram through the same dispatch as every other opcode.
+    #[cfg(feature = "decimal_mode")]
+    pub fn calculate<V: Variant>(instruction: u8, a: u8, b: u8, carry: bool) -> Result<(u8, Flags)> {
+        if instruction == DECIMAL_ADD_OPCODE {
+            return Ok(Alu::calculate_decimal(a, b, carry));
+        }
+
+        V::alu_calculate(instruction, a, b, carry)
+    }
+}
+
+/// The ALU opcode `Alu::calculate` interprets as a decimal add while the
+/// `decimal_mode` feature is enabled, in place of its normal "always
+/// return 0" meaning.
+#[cfg(feature = "decimal_mode")]
+const DECIMAL_ADD_OPCODE: u8 = 0b0011;
+
+#[cfg(feature = "decimal_mode")]
+impl Alu {
+    /// Add two packed binary-coded-decimal bytes, mirroring the 6502
+    /// emulator's `decimal_mode` flag so the 2i can be used to teach decimal
+    /// arithmetic exercises. Only available with the `decimal_mode` feature.
+    ///
+    /// `a` and `b` are each treated as two packed 4 bit decimal digits: the
+    /// low nibbles are added together with `carry` and corrected by 6 if
+    /// their sum exceeds 9, propagating a nibble carry into the high
+    /// nibbles, which are added and corrected the same way. The returned
+    /// byte is the corrected packed result; `zero`/`negative` are derived
+    /// from it as usual, while `carry` reflects the decimal overflow out of
+    /// the high digit.
+    pub fn calculate_decimal(a: u8, b: u8, carry: bool) -> (u8, Flags) {
+        let low_sum = (a & 0x0F) + (b & 0x0F) + carry as u8;
+        let (low, nibble_carry) = if low_sum > 9 {
+            (low_sum + 6, true)
+        } else {
+            (low_sum, false)
         };
 
-        let negative = result & 0b10000000 != 0; // two's complement
+        let high_sum = (a >> 4) + (b >> 4) + nibble_carry as u8;
+        let (high, carry) = if high_sum > 9 {
+            (high_sum + 6, true)
+        } else {
+            (high_sum, false)
+        };
+
+        let result = (high << 4) | (low & 0x0F);
+        let negative = result & 0b10000000 != 0;
         let zero = result == 0;
 
-        return (result, Flags::new(carry, negative, zero));
+        (result, Flags::new(carry, negative, zero))
     }
 }
 
@@ -80,6 +112,7 @@ impl Flags {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::variant::Standard;
 
     #[test]
     fn logic() {
@@ -87,46 +120,46 @@ mod tests {
         let b = 0b00101101;
 
         // pass through a
-        assert_eq!(Alu::calculate(0b0000, a, b, false), (a, Flags::new(false,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0000, a, b, false).unwrap(), (a, Flags::new(false,  true, false)));
         // pass through b
-        assert_eq!(Alu::calculate(0b0001, a, b, false), (b, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0001, a, b, false).unwrap(), (b, Flags::new(false, false, false)));
         // return 0
-        assert_eq!(Alu::calculate(0b0011, a, b, false), (0, Flags::new(false, false,  true)));
+        assert_eq!(Alu::calculate::<Standard>(0b0011, a, b, false).unwrap(), (0, Flags::new(false, false,  true)));
 
         // nor
-        assert_eq!(Alu::calculate(0b0010, a, b, false), (0b00000010, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0010, a, b, false).unwrap(), (0b00000010, Flags::new(false, false, false)));
         // invert (using nor)
-        assert_eq!(Alu::calculate(0b0010, a, a, false), (0b00101011, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0010, b, b, false), (0b11010010, Flags::new(false,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0010, a, a, false).unwrap(), (0b00101011, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0010, b, b, false).unwrap(), (0b11010010, Flags::new(false,  true, false)));
     }
 
     #[test]
     fn addition() {
         // add
-        assert_eq!(Alu::calculate(0b0100,  0,   0, false), ( 0, Flags::new(false, false, true)));
-        assert_eq!(Alu::calculate(0b0100,  0,  19, false), (19, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0100, 47,   0, false), (47, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0100, 47,  19, false), (66, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0100, 47, 236, false), (27, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100,  0,   0, false).unwrap(), ( 0, Flags::new(false, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100,  0,  19, false).unwrap(), (19, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100, 47,   0, false).unwrap(), (47, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100, 47,  19, false).unwrap(), (66, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100, 47, 236, false).unwrap(), (27, Flags::new( true, false, false)));
 
         // add1 (inverts carry)
-        assert_eq!(Alu::calculate(0b0101,  0,   0, false), ( 1, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0101,  0,  19, false), (20, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0101, 47,   0, false), (48, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0101, 47,  19, false), (67, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0101, 47, 236, false), (28, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0101,  0,   0, false).unwrap(), ( 1, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0101,  0,  19, false).unwrap(), (20, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0101, 47,   0, false).unwrap(), (48, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0101, 47,  19, false).unwrap(), (67, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0101, 47, 236, false).unwrap(), (28, Flags::new(false, false, false)));
 
         // addc
-        assert_eq!(Alu::calculate(0b0110, 47,  19, false), (66, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0110, 47,  19,  true), (67, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0110, 47, 236, false), (27, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0110, 47, 236,  true), (28, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0110, 47,  19, false).unwrap(), (66, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0110, 47,  19,  true).unwrap(), (67, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0110, 47, 236, false).unwrap(), (27, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0110, 47, 236,  true).unwrap(), (28, Flags::new( true, false, false)));
 
         // addci (inverts carry)
-        assert_eq!(Alu::calculate(0b0111, 47,  19, false), (67, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0111, 47,  19,  true), (66, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b0111, 47, 236, false), (28, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b0111, 47, 236,  true), (27, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0111, 47,  19, false).unwrap(), (67, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0111, 47,  19,  true).unwrap(), (66, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0111, 47, 236, false).unwrap(), (28, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0111, 47, 236,  true).unwrap(), (27, Flags::new(false, false, false)));
     }
 
     #[test]
@@ -135,50 +168,76 @@ mod tests {
         let b = 0b00101101;
 
         // left shift (using addition)
-        assert_eq!(Alu::calculate(0b0100, a, a, false), (0b10101000, Flags::new( true,  true, false)));
-        assert_eq!(Alu::calculate(0b0100, b, b, false), (0b01011010, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100, a, a, false).unwrap(), (0b10101000, Flags::new( true,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b0100, b, b, false).unwrap(), (0b01011010, Flags::new(false, false, false)));
 
         // logic right shift
-        assert_eq!(Alu::calculate(0b1000, a, 0, false), (0b01101010, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b1000, b, 0, false), (0b00010110, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1000, a, 0, false).unwrap(), (0b01101010, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1000, b, 0, false).unwrap(), (0b00010110, Flags::new( true, false, false)));
 
         // algebraic right shift
-        assert_eq!(Alu::calculate(0b1011, a, 0, false), (0b11101010, Flags::new(false,  true, false)));
-        assert_eq!(Alu::calculate(0b1011, b, 0, false), (0b00010110, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1011, a, 0, false).unwrap(), (0b11101010, Flags::new(false,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1011, b, 0, false).unwrap(), (0b00010110, Flags::new( true, false, false)));
 
         // right rotation
-        assert_eq!(Alu::calculate(0b1001, a, 0, false), (0b01101010, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b1001, b, 0, false), (0b10010110, Flags::new( true,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1001, a, 0, false).unwrap(), (0b01101010, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1001, b, 0, false).unwrap(), (0b10010110, Flags::new( true,  true, false)));
 
         // right carry rotation
-        assert_eq!(Alu::calculate(0b1010, a, 0, false), (0b01101010, Flags::new(false, false, false)));
-        assert_eq!(Alu::calculate(0b1010, a, 0,  true), (0b11101010, Flags::new(false,  true, false)));
-        assert_eq!(Alu::calculate(0b1010, b, 0, false), (0b00010110, Flags::new( true, false, false)));
-        assert_eq!(Alu::calculate(0b1010, b, 0,  true), (0b10010110, Flags::new( true,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1010, a, 0, false).unwrap(), (0b01101010, Flags::new(false, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1010, a, 0,  true).unwrap(), (0b11101010, Flags::new(false,  true, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1010, b, 0, false).unwrap(), (0b00010110, Flags::new( true, false, false)));
+        assert_eq!(Alu::calculate::<Standard>(0b1010, b, 0,  true).unwrap(), (0b10010110, Flags::new( true,  true, false)));
     }
 
     #[test]
     fn flags() {
         // clear carry
-        assert_eq!(Alu::calculate(0b1100, 0, 0, false), (0, Flags::new(false, false, true)));
-        assert_eq!(Alu::calculate(0b1100, 0, 0,  true), (0, Flags::new(false, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1100, 0, 0, false).unwrap(), (0, Flags::new(false, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1100, 0, 0,  true).unwrap(), (0, Flags::new(false, false, true)));
 
         // set carry
-        assert_eq!(Alu::calculate(0b1101, 0, 0, false), (0, Flags::new( true, false, true)));
-        assert_eq!(Alu::calculate(0b1101, 0, 0,  true), (0, Flags::new( true, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1101, 0, 0, false).unwrap(), (0, Flags::new( true, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1101, 0, 0,  true).unwrap(), (0, Flags::new( true, false, true)));
 
         // get carry (equal to 0b0011)
-        assert_eq!(Alu::calculate(0b1110, 0, 0, false), (0, Flags::new(false, false, true)));
-        assert_eq!(Alu::calculate(0b1110, 0, 0,  true), (0, Flags::new( true, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1110, 0, 0, false).unwrap(), (0, Flags::new(false, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1110, 0, 0,  true).unwrap(), (0, Flags::new( true, false, true)));
 
         // invert carry (equal to 0b0011)
-        assert_eq!(Alu::calculate(0b1111, 0, 0, false), (0, Flags::new( true, false, true)));
-        assert_eq!(Alu::calculate(0b1111, 0, 0,  true), (0, Flags::new(false, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1111, 0, 0, false).unwrap(), (0, Flags::new( true, false, true)));
+        assert_eq!(Alu::calculate::<Standard>(0b1111, 0, 0,  true).unwrap(), (0, Flags::new(false, false, true)));
     }
 
     #[test]
     #[should_panic(expected = "Invalid alu instruction")]
     fn invalid_instruction() {
-        Alu::calculate(0b10000, 0, 0, false);
+        Alu::calculate::<Standard>(0b10000, 0, 0, false).unwrap();
+    }
+
+    #[cfg(feature = "decimal_mode")]
+    #[test]
+    fn decimal_addition() {
+        // 12 + 34 = 46, no nibble or decimal carry
+        assert_eq!(Alu::calculate_decimal(0x12, 0x34, false), (0x46, Flags::new(false, false, false)));
+
+        // 58 + 27 = 85, low nibble overflows (8 + 7 = 15 -> corrected to 5, carry 1)
+        assert_eq!(Alu::calculate_decimal(0x58, 0x27, false), (0x85, Flags::new(false, true, false)));
+
+        // 99 + 1 = 100, truncated to 00 with decimal carry out
+        assert_eq!(Alu::calculate_decimal(0x99, 0x01, false), (0x00, Flags::new(true, false, true)));
+
+        // carry-in is added into the low digit
+        assert_eq!(Alu::calculate_decimal(0x00, 0x00, true), (0x01, Flags::new(false, false, false)));
+    }
+
+    #[cfg(feature = "decimal_mode")]
+    #[test]
+    fn decimal_addition_is_reachable_through_dispatch() {
+        // a microprogram selects decimal add the same way it selects any
+        // other opcode, through `Alu::calculate`, not by calling
+        // `calculate_decimal` directly.
+        assert_eq!(Alu::calculate::<Standard>(DECIMAL_ADD_OPCODE, 0x58, 0x27, false).unwrap(),
+                   Alu::calculate_decimal(0x58, 0x27, false));
     }
 }
@@ -5,15 +5,33 @@
 
 use super::{Error, Result};
 use std::cell::RefCell;
+use std::result;
 
-/// Bus of the 2i.
+/// An address- and error-generic bus interface, modelled after
+/// `emulator-hal`'s `BusAccess`.
 ///
-/// Represents an interface of the 2i bus with 8 bit data and addressing.
-pub trait Bus {
-    fn read(&self, address: u8) -> Result<u8>;
-    fn write(&self, address: u8, value: u8) -> Result<()>;
+/// Implementors choose their own `Address` and `Error` types, so a peripheral
+/// can report precise errors (eg. "write to read-only region at 0x..") or be
+/// reused on an emulator core with a different address width, instead of
+/// being tied to the 2i's own [`Error`].
+pub trait BusAccess {
+    type Address;
+    type Error;
+
+    fn read(&self, address: Self::Address) -> result::Result<u8, Self::Error>;
+    fn write(&self, address: Self::Address, value: u8) -> result::Result<(), Self::Error>;
 }
 
+/// Bus of the 2i.
+///
+/// The 2i's native bus: an 8 bit address space reporting the emulator's own
+/// [`Error`]. This is the default instantiation of [`BusAccess`] that
+/// `Ram`, `IoRegisters` and the `Cpu` use; implement `BusAccess<Address = u8,
+/// Error = Error>` to get it for free.
+pub trait Bus: BusAccess<Address = u8, Error = Error> {}
+
+impl<T: BusAccess<Address = u8, Error = Error> + ?Sized> Bus for T {}
+
 /// Ram of the 2i.
 ///
 /// Represents the 8 bit ram of the 2i.
@@ -53,7 +71,10 @@ impl<'a> Default for Ram<'a> {
     }
 }
 
-impl<'a> Bus for Ram<'a> {
+impl<'a> BusAccess for Ram<'a> {
+    type Address = u8;
+    type Error = Error;
+
     fn read(&self, address: u8) -> Result<u8> {
         for &(first_address, last_address, bus) in self.overlays.iter() {
             if address >= first_address && address <= last_address {
@@ -103,12 +124,15 @@ impl IoRegisters {
     }
 }
 
-impl Bus for IoRegisters {
+impl BusAccess for IoRegisters {
+    type Address = u8;
+    type Error = Error;
+
     fn read(&self, address: u8) -> Result<u8> {
         if address >= 0xFC {
             Ok(self.input.borrow()[(address - 0xFC) as usize])
         } else {
-            Err(Error::Bus("Only supports reading from input registers"))
+            Err(Error::Bus(address, "Only supports reading from input registers"))
         }
     }
     fn write(&self, address: u8, value: u8) -> Result<()> {
@@ -116,9 +140,9 @@ impl Bus for IoRegisters {
             self.output.borrow_mut()[(address - 0xFE) as usize] = value;
             Ok(())
         } else if address >= 0xFC {
-            Err(Error::Bus("Cannot write to input register"))
+            Err(Error::Bus(address, "Cannot write to input register"))
         } else {
-            Err(Error::Bus("Only supports writing to output registers"))
+            Err(Error::Bus(address, "Only supports writing to output registers"))
         }
     }
 }
@@ -202,4 +226,36 @@ mod tests {
         assert!(io.write(0xFC, 0).is_err());
         assert!(io.write(0xFD, 0).is_err());
     }
+
+    /// A peripheral with its own address and error types, demonstrating that
+    /// `BusAccess` doesn't force a peripheral into the 2i's `u8`/`Error`.
+    struct ReadOnlyRegister {
+        value: u8,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum ReadOnlyError {
+        WriteToReadOnlyRegister(u16),
+    }
+
+    impl BusAccess for ReadOnlyRegister {
+        type Address = u16;
+        type Error = ReadOnlyError;
+
+        fn read(&self, _address: u16) -> result::Result<u8, ReadOnlyError> {
+            Ok(self.value)
+        }
+        fn write(&self, address: u16, _value: u8) -> result::Result<(), ReadOnlyError> {
+            Err(ReadOnlyError::WriteToReadOnlyRegister(address))
+        }
+    }
+
+    #[test]
+    fn custom_peripheral_with_own_error_type() {
+        let register = ReadOnlyRegister { value: 42 };
+
+        assert_eq!(register.read(0x1234).unwrap(), 42);
+        assert_eq!(register.write(0x1234, 0).unwrap_err(),
+            ReadOnlyError::WriteToReadOnlyRegister(0x1234));
+    }
 }
@@ -0,0 +1,219 @@
+//! Control-flow graph extraction and Graphviz export.
+//!
+//! `read_reachable_program` already follows a microprogram's control flow
+//! from address 0, fanning out on conditional jumps, but only hands back the
+//! flat list of reachable instructions. `build_cfg` exposes that same
+//! traversal as an explicit graph of nodes and typed edges, and `write_dot`
+//! renders it in Graphviz DOT format for visualization.
+
+use std::io::{self, Write};
+
+use super::instruction::Instruction;
+
+/// One address reachable from the start of a microprogram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Node {
+    pub address: u8,
+    /// `None` if the address is reachable but was never assigned an
+    /// instruction; such a node behaves as a default NOP/JMP 0 instruction.
+    pub instruction: Option<Instruction>,
+}
+
+/// How two nodes are connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The only successor of an instruction with no address control (MAC 00).
+    Fallthrough,
+    /// The successor taken by a conditional jump (MAC != 00) when the tested
+    /// condition is false.
+    ConditionalLow,
+    /// The successor taken by a conditional jump (MAC != 00) when the tested
+    /// condition is true.
+    ConditionalHigh,
+}
+
+/// A directed edge from one node address to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: u8,
+    pub to: u8,
+    pub kind: EdgeKind,
+}
+
+/// The control-flow graph of a microprogram: every address reachable from
+/// address 0, plus the edges between them.
+#[derive(Debug, Clone, Default)]
+pub struct Cfg {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// Build the control-flow graph of `instructions`, starting from address 0
+/// and following `Instruction::get_next_instruction_address`, fanning out
+/// into a `ConditionalLow`/`ConditionalHigh` pair of edges whenever
+/// `get_address_control() != 0`.
+pub fn build_cfg(instructions: &[Option<Instruction>; 32]) -> Cfg {
+    #[derive(Clone, Copy)]
+    enum State {
+        Unvisited,
+        Visited,
+        Instruction(Instruction),
+    }
+
+    let mut state = [State::Unvisited; 32];
+    state[0] = match instructions[0] {
+        Some(inst) => State::Instruction(inst),
+        None => State::Visited,
+    };
+
+    let mut cfg = Cfg::default();
+
+    // Since instructions can jump to earlier addresses, iterate until no new
+    // address is discovered.
+    let mut finished = false;
+    while !finished {
+        finished = true;
+
+        for from in 0..state.len() {
+            if let State::Instruction(inst) = state[from] {
+                let na = inst.get_next_instruction_address();
+
+                let targets: Vec<(u8, EdgeKind)> = if inst.get_address_control() == 0 {
+                    vec![(na, EdgeKind::Fallthrough)]
+                } else {
+                    vec![(na & !1u8, EdgeKind::ConditionalLow), (na | 1u8, EdgeKind::ConditionalHigh)]
+                };
+
+                for (to, kind) in targets {
+                    let edge = Edge { from: from as u8, to, kind };
+                    if !cfg.edges.contains(&edge) {
+                        cfg.edges.push(edge);
+                    }
+
+                    let to = to as usize;
+                    if let State::Unvisited = state[to] {
+                        finished = false;
+                        state[to] = match instructions[to] {
+                            Some(inst) => State::Instruction(inst),
+                            None => State::Visited,
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    for (address, s) in state.iter().enumerate() {
+        let instruction = match s {
+            State::Unvisited => continue,
+            State::Visited => None,
+            State::Instruction(inst) => Some(*inst),
+        };
+
+        cfg.nodes.push(Node { address: address as u8, instruction });
+    }
+
+    cfg
+}
+
+/// Render `cfg` as a Graphviz DOT digraph: one node per reachable address
+/// (synthesized NOP/JMP 0 nodes are labelled as such) and one edge per
+/// control-flow transition, labelled with its `EdgeKind`.
+pub fn write_dot<W: Write>(cfg: &Cfg, out: &mut W) -> io::Result<()> {
+    writeln!(out, "digraph cfg {{")?;
+
+    for node in &cfg.nodes {
+        let label = match node.instruction {
+            Some(inst) => inst.to_mnemonic(Some(node.address as usize)),
+            None => "(synthesized NOP; JMP 0)".to_string(),
+        };
+
+        writeln!(out, "    {0} [label=\"{0}: {1}\"];", node.address, escape_dot(&label))?;
+    }
+
+    for edge in &cfg.edges {
+        let style = match edge.kind {
+            EdgeKind::Fallthrough => "label=\"\"",
+            EdgeKind::ConditionalLow => "label=\"0\", style=dashed",
+            EdgeKind::ConditionalHigh => "label=\"1\", style=dashed",
+        };
+
+        writeln!(out, "    {} -> {} [{}];", edge.from, edge.to, style)?;
+    }
+
+    writeln!(out, "}}")
+}
+
+fn escape_dot(string: &str) -> String {
+    string.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use super::super::parse::parse_instructions;
+
+    #[test]
+    fn build_cfg_follows_backjumps() {
+        let program = Cursor::new("\
+            00000: 00 00100 000000000000000000\n\
+            00001: 00 11111 000000000000000000\n\
+            00010: 00 00001 000000000000000000\n\
+            00100: 00 00010 000000000000000000\n\
+            11111: 00 00000 000000000000000000\n\
+        ".to_owned());
+        let instructions = parse_instructions(program).unwrap();
+        let cfg = build_cfg(&instructions);
+
+        assert_eq!(cfg.nodes.iter().map(|n| n.address).collect::<Vec<_>>(), &[0, 1, 2, 4, 31]);
+        assert!(cfg.edges.contains(&Edge { from: 4, to: 2, kind: EdgeKind::Fallthrough }));
+        assert!(cfg.edges.contains(&Edge { from: 31, to: 0, kind: EdgeKind::Fallthrough }));
+    }
+
+    #[test]
+    fn build_cfg_fans_out_conditional_jumps() {
+        let program = Cursor::new("\
+            00000: 11 00010 000000000000000000\n\
+            00010: 00 00000 000000000000000000\n\
+            00011: 00 00000 000000000000000000\n\
+        ".to_owned());
+        let instructions = parse_instructions(program).unwrap();
+        let cfg = build_cfg(&instructions);
+
+        assert_eq!(cfg.nodes.iter().map(|n| n.address).collect::<Vec<_>>(), &[0, 2, 3]);
+        assert!(cfg.edges.contains(&Edge { from: 0, to: 2, kind: EdgeKind::ConditionalLow }));
+        assert!(cfg.edges.contains(&Edge { from: 0, to: 3, kind: EdgeKind::ConditionalHigh }));
+    }
+
+    #[test]
+    fn build_cfg_marks_synthesized_nodes() {
+        let program = Cursor::new("\
+            00000: 00 00010 000000000000000000\n\
+        ".to_owned());
+        let instructions = parse_instructions(program).unwrap();
+        let cfg = build_cfg(&instructions);
+
+        let synthesized = cfg.nodes.iter().find(|n| n.address == 2).unwrap();
+        assert_eq!(synthesized.instruction, None);
+    }
+
+    #[test]
+    fn write_dot_omits_unreachable_and_marks_synthesized_nodes() {
+        let program = Cursor::new("\
+            00000: 00 00010 000000000000000000\n\
+            00101: 00 00000 000000000000000000\n\
+        ".to_owned());
+        let instructions = parse_instructions(program).unwrap();
+        let cfg = build_cfg(&instructions);
+
+        let mut dot = Vec::new();
+        write_dot(&cfg, &mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        assert!(dot.contains("digraph cfg {"));
+        assert!(!dot.contains("\n    5 ["));
+        assert!(dot.contains("(synthesized NOP; JMP 0)"));
+    }
+}
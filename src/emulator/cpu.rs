@@ -2,16 +2,27 @@
 //!
 //! This module contains the cpu used in the 2i.
 
+use std::marker::PhantomData;
+
+use log::{debug, trace};
+
 use super::{Error, Result};
 use super::alu::{Alu, Flags};
-use super::bus::Bus;
+use super::bus::{Bus, BusAccess};
 use super::instruction::Instruction;
+use super::interrupt::{InterruptController, InterruptLine};
+use super::variant::{AddressSource, Standard, Variant};
 
 /// Cpu of the 2i.
 ///
 /// Represents the 8 bit cpu of the 2i with 8 registers that are 8 bit wide and
 /// the three status registers (carry, negative, zero).
 ///
+/// Parameterized over a `Variant`, defaulting to `Standard`, selecting which
+/// ALU opcode table and address-control decode table the cpu uses, so
+/// microprograms written for one course revision fault cleanly on another
+/// instead of silently executing a different op.
+///
 /// # Examples:
 ///
 /// ```
@@ -27,11 +38,11 @@ use super::instruction::Instruction;
 /// assert_eq!(6, cpu.inspect_registers()[0]);
 /// ```
 #[derive(Default)]
-pub struct Cpu {
+pub struct Cpu<V: Variant = Standard> {
     registers: [u8; 8],
     flag_register: Flags,
-    stored_interrupt: bool,
-    volatile_interrupt: bool,
+    interrupts: InterruptController,
+    variant: PhantomData<V>,
 }
 
 impl Cpu {
@@ -39,11 +50,15 @@ impl Cpu {
     pub fn new() -> Cpu {
         Cpu::default()
     }
+}
+
+impl<V: Variant> Cpu<V> {
 
     /// Execute the given instruction on the cpu using the given, bus, input
     /// and output. Returns the address of the next instruction and the alu flags.
     pub fn execute_instruction<B: Bus>(&mut self, inst: Instruction, bus: &mut B) -> Result<(usize, Flags)> {
         // Determine alu input a (bus or register)
+        let mut bus_read = None;
         let a = if inst.is_alu_input_a_bus() {
             if ! inst.is_bus_enabled() {
                 return Err(Error::Cpu("Cannot read from disabled bus"));
@@ -51,7 +66,10 @@ impl Cpu {
                 return Err(Error::Cpu("Cannot read from bus while it is in write mode"));
             }
 
-            try!(bus.read(self.registers[inst.get_register_address_a()]))
+            let address = self.registers[inst.get_register_address_a()];
+            let value = try!(bus.read(address));
+            bus_read = Some((address, value));
+            value
         } else {
             self.registers[inst.get_register_address_a()]
         };
@@ -64,8 +82,8 @@ impl Cpu {
         };
 
         // Calculate result using alu
-        let (result, flags) = Alu::calculate(inst.get_alu_instruction(), a, b,
-            self.flag_register.carry());
+        let (result, flags) = try!(Alu::calculate::<V>(inst.get_alu_instruction(), a, b,
+            self.flag_register.carry()));
 
         // Write result to registers
         if inst.should_write_register() {
@@ -77,8 +95,11 @@ impl Cpu {
         }
 
         // Write results to the bus
+        let mut bus_write = None;
         if inst.is_bus_enabled() && inst.is_bus_writable() {
-            try!(bus.write(self.registers[inst.get_register_address_a()], result));
+            let address = self.registers[inst.get_register_address_a()];
+            try!(bus.write(address, result));
+            bus_write = Some((address, result));
         }
 
         // Store flags in the flag register
@@ -89,24 +110,28 @@ impl Cpu {
         // Calculate and return the next instruction address
         let next_address = self.calculate_next_instruction_address(inst, flags);
 
-        // Reset interrupts (stored only if MAC = 111)
-        self.volatile_interrupt = false;
+        // Acknowledge interrupts (the volatile interrupt only ever applies
+        // to a single instruction, the stored interrupt is acknowledged
+        // once an instruction with MAC = 111 actually consumes it)
+        self.interrupts.acknowledge_volatile();
         if inst.get_address_control() == 0b11 &&
            inst.get_next_instruction_address() & 0b00001 == 0b1 {
-            self.stored_interrupt = false;
+            self.interrupts.acknowledge_stored();
         }
 
+        self.trace_execution(inst, a, b, result, flags, next_address, bus_read, bus_write);
+
         Ok((next_address as usize, flags))
     }
 
     /// Enable the volatile interrupt (MAC 010) for the next instruction executed
     pub fn trigger_volatile_interrupt(&mut self) {
-        self.volatile_interrupt = true;
+        self.interrupts.assert(InterruptLine::A, None);
     }
 
     /// Enable the stored interrupt (MAC 111) until used by any instruction
     pub fn trigger_stored_interrupt(&mut self){
-        self.stored_interrupt = true;
+        self.interrupts.assert(InterruptLine::B, None);
     }
 
     /// Direct access to the registers.
@@ -119,14 +144,20 @@ impl Cpu {
         &mut self.flag_register
     }
 
+    /// Direct access to the interrupt controller, eg. for peripherals that
+    /// need to assert an interrupt request line themselves.
+    pub fn inspect_interrupts(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
+    }
+
     /// Check if the volatile interrupt is active for the next instruction
     pub fn check_volatile_interrupt(&self) -> bool {
-        self.volatile_interrupt
+        self.interrupts.is_pending(InterruptLine::A)
     }
 
     /// Check if the stored interrupt is active
     pub fn check_stored_interrupt(&self) -> bool {
-        self.stored_interrupt
+        self.interrupts.is_pending(InterruptLine::B)
     }
 
     /// Calculate the next instruction address based on the current instruction
@@ -134,34 +165,49 @@ impl Cpu {
     fn calculate_next_instruction_address(&self, inst: Instruction, flags: Flags) -> u8 {
         let next_address = inst.get_next_instruction_address();
         let next_address_base = next_address & 0b11110; // Mask off last bit
+        let na0 = next_address & 0b00001;
 
-        match inst.get_address_control() << 1 | (next_address & 0b00001) {
-            0b000 | 0b001 => {
+        match V::decode_address_control(inst.get_address_control(), na0) {
+            AddressSource::Direct => {
                 next_address
             }
-            0b010 => {
-                next_address_base | self.volatile_interrupt as u8
+            AddressSource::VolatileInterrupt => {
+                self.interrupts.resolve(InterruptLine::A, next_address_base)
             }
-            0b011 => {
+            AddressSource::StoredCarry => {
                 next_address_base | self.flag_register.carry() as u8
             }
-            0b100 => {
+            AddressSource::Carry => {
                 next_address_base | flags.carry() as u8
             }
-            0b101 => {
+            AddressSource::Zero => {
                 next_address_base | flags.zero() as u8
             }
-            0b110 => {
+            AddressSource::Negative => {
                 next_address_base | flags.negative() as u8
             }
-            0b111 => {
-                next_address_base | self.stored_interrupt as u8
-            }
-            _ => {
-                panic!("Invalid address control")
+            AddressSource::StoredInterrupt => {
+                self.interrupts.resolve(InterruptLine::B, next_address_base)
             }
         }
     }
+
+    /// Emit a structured record of the just-executed instruction through the
+    /// `log` crate, for capturing a full execution transcript without
+    /// touching the CLI's `status` display. A no-op unless a logger is
+    /// installed, so this has zero overhead by default.
+    ///
+    /// Logs the decoded mnemonic at `trace` level and the resolved alu
+    /// inputs/result, the produced flags vs. the flags actually stored, any
+    /// bus access and the computed next address at `debug` level.
+    fn trace_execution(&self, inst: Instruction, a: u8, b: u8, result: u8, flags: Flags,
+                        next_address: u8, bus_read: Option<(u8, u8)>, bus_write: Option<(u8, u8)>) {
+        trace!("{}", inst.to_mnemonic(None));
+
+        debug!("a={:#04x} b={:#04x} result={:#04x} flags={:?} stored_flags={:?} \
+                bus_read={:?} bus_write={:?} next_address={:05b}",
+            a, b, result, flags, self.flag_register, bus_read, bus_write, next_address);
+    }
 }
 
 #[cfg(test)]
@@ -177,8 +223,13 @@ mod tests {
         let na = |inst: u32, flags, carry, volatile_int, stored_int| {
             let inst = Instruction::new(inst << 18).unwrap();
             let mut cpu = Cpu::default();
-            cpu.volatile_interrupt = volatile_int;
-            cpu.stored_interrupt = stored_int;
+            let _: &Cpu = &cpu; // Use the standard variant
+            if volatile_int {
+                cpu.interrupts.assert(InterruptLine::A, None);
+            }
+            if stored_int {
+                cpu.interrupts.assert(InterruptLine::B, None);
+            }
             cpu.flag_register = Flags::new(carry, false, false);
             cpu.calculate_next_instruction_address(inst, flags)
         };
@@ -0,0 +1,166 @@
+//! Bus-attached peripheral devices.
+//!
+//! `Ram` and `IoRegisters` in the `bus` module cover the base memory and the
+//! four fixed I/O registers, but `Ram::add_overlay` lets any other `Bus`
+//! implementation claim a range of the address space instead. This module
+//! collects such peripherals, currently just a single countdown `Timer`.
+
+use std::cell::RefCell;
+
+use super::{Error, Result};
+use super::bus::BusAccess;
+use super::interrupt::{InterruptController, InterruptLine};
+
+/// A free-running countdown timer that can be attached to the bus as an
+/// overlay on two addresses.
+///
+/// Offset 0 is the reload register, holding the value the counter is reset
+/// to once it expires; writing it also loads the counter. Offset 1 is the status register: bit 0 enables the
+/// timer and bit 1 is set once the counter has expired, read-only other
+/// than being cleared by any write to the register. Each call to `tick`
+/// while enabled decrements the counter and, once it underflows, reloads it
+/// and raises the configured interrupt line.
+pub struct Timer {
+    line: InterruptLine,
+    reload: RefCell<u8>,
+    counter: RefCell<u8>,
+    enabled: RefCell<bool>,
+    expired: RefCell<bool>,
+}
+
+impl Timer {
+    /// Create a new, disabled timer with a reload value and counter of zero
+    /// that raises `line` once it expires.
+    pub fn new(line: InterruptLine) -> Timer {
+        Timer {
+            line,
+            reload: RefCell::new(0),
+            counter: RefCell::new(0),
+            enabled: RefCell::new(false),
+            expired: RefCell::new(false),
+        }
+    }
+
+    /// Advance the timer by one emulated step.
+    ///
+    /// Does nothing while disabled. Otherwise decrements the counter, or,
+    /// once it has reached zero, reloads it from the reload register, marks
+    /// the timer as expired and asserts its interrupt line.
+    pub fn tick(&self, interrupts: &mut InterruptController) {
+        if ! *self.enabled.borrow() {
+            return;
+        }
+
+        let mut counter = self.counter.borrow_mut();
+        if *counter == 0 {
+            *counter = *self.reload.borrow();
+            *self.expired.borrow_mut() = true;
+            interrupts.assert(self.line, Some("timer"));
+        } else {
+            *counter -= 1;
+        }
+    }
+}
+
+impl BusAccess for Timer {
+    type Address = u8;
+    type Error = Error;
+
+    fn read(&self, address: u8) -> Result<u8> {
+        match address {
+            0 => Ok(*self.reload.borrow()),
+            1 => Ok(*self.enabled.borrow() as u8 | (*self.expired.borrow() as u8) << 1),
+            _ => Err(Error::Bus(address, "Timer only exposes a reload and a status register")),
+        }
+    }
+
+    fn write(&self, address: u8, value: u8) -> Result<()> {
+        match address {
+            0 => {
+                *self.reload.borrow_mut() = value;
+                *self.counter.borrow_mut() = value;
+                Ok(())
+            }
+            1 => {
+                *self.enabled.borrow_mut() = value & 0b1 != 0;
+                *self.expired.borrow_mut() = false;
+                Ok(())
+            }
+            _ => Err(Error::Bus(address, "Timer only exposes a reload and a status register")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload_register_is_readable_and_writable() {
+        let timer = Timer::new(InterruptLine::A);
+
+        timer.write(0, 42).unwrap();
+        assert_eq!(timer.read(0).unwrap(), 42);
+    }
+
+    #[test]
+    fn status_register_reports_enabled_and_expired() {
+        let timer = Timer::new(InterruptLine::A);
+        let mut interrupts = InterruptController::new();
+
+        assert_eq!(timer.read(1).unwrap(), 0b00);
+
+        timer.write(1, 0b1).unwrap();
+        assert_eq!(timer.read(1).unwrap(), 0b01);
+
+        timer.tick(&mut interrupts);
+        assert_eq!(timer.read(1).unwrap(), 0b11);
+
+        // Writing to the status register clears the expired bit
+        timer.write(1, 0b1).unwrap();
+        assert_eq!(timer.read(1).unwrap(), 0b01);
+    }
+
+    #[test]
+    fn disabled_timer_does_not_tick_or_interrupt() {
+        let timer = Timer::new(InterruptLine::A);
+        let mut interrupts = InterruptController::new();
+
+        timer.write(0, 3).unwrap();
+        for _ in 0..10 {
+            timer.tick(&mut interrupts);
+        }
+
+        assert!(!interrupts.is_pending(InterruptLine::A));
+    }
+
+    #[test]
+    fn counter_expires_reloads_and_raises_interrupt() {
+        let timer = Timer::new(InterruptLine::B);
+        let mut interrupts = InterruptController::new();
+
+        timer.write(0, 2).unwrap(); // reload = 2
+        timer.write(1, 0b1).unwrap(); // enable
+
+        timer.tick(&mut interrupts); // counter: 2 -> 1
+        assert!(!interrupts.is_pending(InterruptLine::B));
+
+        timer.tick(&mut interrupts); // counter: 1 -> 0
+        assert!(!interrupts.is_pending(InterruptLine::B));
+
+        timer.tick(&mut interrupts); // counter at 0, reloads to 2 and fires
+        assert!(interrupts.is_pending(InterruptLine::B));
+
+        interrupts.deassert(InterruptLine::B);
+        timer.tick(&mut interrupts); // counter: 2 -> 1
+        assert!(!interrupts.is_pending(InterruptLine::B));
+    }
+
+    #[test]
+    fn invalid_address_is_an_error() {
+        let timer = Timer::new(InterruptLine::A);
+
+        assert!(timer.read(2).is_err());
+        assert!(timer.write(2, 0).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Source positions and annotated reports for parse errors.
+//!
+//! This is deliberately small: it only has to locate a single span inside
+//! one line of a 2i program and render a caret underline pointing at it,
+//! not lay out multi-file, multi-label reports.
+
+use std::fmt;
+
+/// A location in a piece of source text, identified by its 1-based line and
+/// column and how many characters it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, col: usize, len: usize) -> Span {
+        Span { line, col, len }
+    }
+}
+
+/// A parse error with an attached source span that can render itself as an
+/// annotated report pointing at the offending part of a line.
+///
+/// # Examples
+///
+/// ```
+/// use emulator::diagnostics::{Diagnostic, Span};
+///
+/// let diag = Diagnostic::new(Span::new(3, 3, 1), "expected '0' or '1', found 'a'",
+///     "00a0010000000000000000000");
+/// let report = diag.render();
+/// assert!(report.contains("line 3, column 3"));
+/// assert!(report.contains("00a0010000000000000000000"));
+/// assert!(report.ends_with("^"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+    /// The full text of the line the span is located on, used to render the
+    /// annotated report.
+    pub line_text: String,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>, T: Into<String>>(span: Span, message: S, line_text: T) -> Diagnostic {
+        Diagnostic { span, message: message.into(), line_text: line_text.into() }
+    }
+
+    /// Render this diagnostic as a caret-annotated report.
+    pub fn render(&self) -> String {
+        let gutter = self.span.line.to_string().len();
+        let caret = format!("{}{}",
+            " ".repeat(self.span.col.saturating_sub(1)),
+            "^".repeat(self.span.len.max(1)));
+
+        format!(
+            "error: {message}\n\
+             {blank:gutter$} --> line {line}, column {col}\n\
+             {blank:gutter$} |\n\
+             {line:gutter$} | {text}\n\
+             {blank:gutter$} | {caret}",
+            message = self.message,
+            blank = "",
+            gutter = gutter,
+            line = self.span.line,
+            col = self.span.col,
+            text = self.line_text,
+            caret = caret,
+        )
+    }
+
+    /// Render this diagnostic the same way as `render`, but with the
+    /// surrounding text in German, for the CLI front-ends.
+    pub fn render_de(&self) -> String {
+        let gutter = self.span.line.to_string().len();
+        let caret = format!("{}{}",
+            " ".repeat(self.span.col.saturating_sub(1)),
+            "^".repeat(self.span.len.max(1)));
+
+        format!(
+            "Fehler in Zeile {line}, Spalte {col}: {message}\n\
+             {blank:gutter$} |\n\
+             {line:gutter$} | {text}\n\
+             {blank:gutter$} | {caret}",
+            message = self.message,
+            blank = "",
+            gutter = gutter,
+            line = self.span.line,
+            col = self.span.col,
+            text = self.line_text,
+            caret = caret,
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_points_at_span() {
+        let diag = Diagnostic::new(Span::new(1, 3, 1), "expected '0' or '1', found 'a'",
+            "00a0010000000000000000000");
+
+        let expected = concat!(
+            "error: expected '0' or '1', found 'a'\n",
+            "  --> line 1, column 3\n",
+            "  |\n",
+            "1 | 00a0010000000000000000000\n",
+            "  |   ^");
+        assert_eq!(diag.render(), expected);
+    }
+
+    #[test]
+    fn render_pads_gutter_for_multi_digit_lines() {
+        let diag = Diagnostic::new(Span::new(12, 1, 26), "instruction is wider than 25 bits",
+            "000000000000000000000000000");
+
+        let expected = concat!(
+            "error: instruction is wider than 25 bits\n",
+            "   --> line 12, column 1\n",
+            "   |\n",
+            "12 | 000000000000000000000000000\n",
+            "   | ^^^^^^^^^^^^^^^^^^^^^^^^^^");
+        assert_eq!(diag.render(), expected);
+    }
+
+    #[test]
+    fn render_de_points_at_span() {
+        let diag = Diagnostic::new(Span::new(1, 3, 1), "expected '0' or '1', found 'a'",
+            "00a0010000000000000000000");
+
+        let expected = concat!(
+            "Fehler in Zeile 1, Spalte 3: expected '0' or '1', found 'a'\n",
+            "  |\n",
+            "1 | 00a0010000000000000000000\n",
+            "  |   ^");
+        assert_eq!(diag.render_de(), expected);
+    }
+}
@@ -252,6 +252,364 @@ impl Instruction {
             format!("{}{}{}{}", output, result, address_control, change_flags)
         }
     }
+    /// Parse a textual representation produced by `to_mnemonic` back into an
+    /// `Instruction`.
+    ///
+    /// The optional `address` has the same meaning as in `to_mnemonic`: it is
+    /// the address the instruction itself will be placed at and is only
+    /// needed to resolve the implicit "fall through to the next address" and
+    /// `LOOP` forms, which omit the next address entirely. Every other
+    /// address control form (`JMP`, `CF`, `CO`, `ZO`, `NO`, `INTA`, `INTB`) is
+    /// fully self-contained and does not require `address`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use emulator::Instruction;
+    ///
+    /// let inst = Instruction::from_mnemonic("R0 = R0 + 6", Some(0)).unwrap();
+    /// assert_eq!(Instruction::new(0b00_00001_00_000_0110_01_01_0100_0).unwrap(), inst);
+    /// ```
+    pub fn from_mnemonic(mnemonic: &str, address: Option<usize>) -> Result<Instruction> {
+        // Splitting on ';' is safe because none of the operand forms (Rn,
+        // (Rn), hex constants) ever contain a semicolon themselves.
+        let parts: Vec<&str> = mnemonic.split(';').map(|s| s.trim()).collect();
+
+        let head = parts[0];
+        let mut index = 1;
+
+        // HLDC/SETC/INVC are tied to the alu opcode and, if present, are
+        // always the modifier directly following the head.
+        let carry_modifier = match parts.get(index).copied() {
+            Some(m @ "HLDC") | Some(m @ "SETC") | Some(m @ "INVC") => {
+                index += 1;
+                Some(m)
+            }
+            _ => None,
+        };
+
+        let address_modifier = match parts.get(index).copied() {
+            Some("CHFL") | None => None,
+            Some(m) => {
+                index += 1;
+                Some(m)
+            }
+        };
+
+        let store_flags = match parts.get(index).copied() {
+            Some("CHFL") => {
+                index += 1;
+                true
+            }
+            _ => false,
+        };
+
+        if index != parts.len() {
+            return Err(Error::Instruction("Unexpected trailing modifier"));
+        }
+
+        let (alu, mrgwe, mrgws, busen, buswr, addr_a, const_or_reg_b, malua, malub) =
+            Self::parse_head(head, carry_modifier)?;
+        let (mac, na) = Self::parse_address_control(address_modifier, address)?;
+
+        let mut bits: u32 = 0;
+        if store_flags { bits |= 1 << 0; }
+        bits |= (alu as u32 & 0b1111) << 1;
+        if malub { bits |= 1 << 5; }
+        if malua { bits |= 1 << 6; }
+        if mrgwe { bits |= 1 << 7; }
+        if mrgws { bits |= 1 << 8; }
+        bits |= (const_or_reg_b as u32 & 0b1111) << 9;
+        bits |= (addr_a as u32 & 0b111) << 13;
+        if busen { bits |= 1 << 16; }
+        if buswr { bits |= 1 << 17; }
+        bits |= (na as u32 & 0b11111) << 18;
+        bits |= (mac as u32 & 0b11) << 23;
+
+        Instruction::new(bits)
+    }
+
+    /// Parse the operation part of a mnemonic (everything before the first
+    /// `;`) into the fields it encodes.
+    ///
+    /// Returns `(alu, mrgwe, mrgws, busen, buswr, addr_a, const_or_reg_b,
+    /// malua, malub)`.
+    fn parse_head(head: &str, carry: Option<&str>)
+        -> Result<(u8, bool, bool, bool, bool, u8, u8, bool, bool)> {
+        if head == "NOP" {
+            if carry.is_some() {
+                return Err(Error::Instruction("NOP cannot carry a HLDC/SETC/INVC modifier"));
+            }
+            return Ok((0b0000, false, false, false, false, 0, 0, false, false));
+        }
+
+        if let Some(rest) = head.strip_prefix("TEST ") {
+            if carry.is_some() {
+                return Err(Error::Instruction("TEST cannot carry a HLDC/SETC/INVC modifier"));
+            }
+            let (reg_a, malua) = Self::parse_operand_a(rest)?;
+            return Ok((0b0001, false, false, false, false, reg_a, 0, malua, false));
+        }
+
+        let eq = head.find(" = ")
+            .ok_or(Error::Instruction("Expected 'TEST Rn', 'NOP' or an assignment"))?;
+        let (output, expr) = (&head[..eq], &head[eq + 3..]);
+
+        let (bus_addr, write_reg) = if let Some(inner) = output.strip_prefix('(') {
+            if let Some(comma) = inner.find("),") {
+                let reg = Self::parse_register(&inner[..comma])?;
+                let dest = Self::parse_register(&inner[comma + 2..])?;
+                (Some(reg), Some(dest))
+            } else {
+                let reg = Self::parse_register(inner.strip_suffix(')')
+                    .ok_or(Error::Instruction("Unterminated '(Rn)' output"))?)?;
+                (Some(reg), None)
+            }
+        } else {
+            (None, Some(Self::parse_register(output)?))
+        };
+
+        let (alu, op_a, op_b) = Self::parse_expr(expr, carry)?;
+
+        let malua = op_a.map_or(false, |(_, malua)| malua);
+        let malub = matches!(op_b, Some(BOperand::Const(_)));
+        let op_a_reg = op_a.map(|(reg, _)| reg);
+        let op_b_reg = match op_b {
+            Some(BOperand::Reg(reg)) => Some(reg),
+            _ => None,
+        };
+        let const_val = match op_b {
+            Some(BOperand::Const(c)) => Some(c & 0b1111),
+            _ => None,
+        };
+
+        if let (Some(bus_reg), Some(a_reg)) = (bus_addr, op_a_reg) {
+            if bus_reg != a_reg {
+                return Err(Error::Instruction(
+                    "Bus address and alu input a must use the same register"));
+            }
+        }
+        let addr_a_fixed = bus_addr.or(op_a_reg);
+
+        let (addr_a, const_or_reg_b, mrgws) = match write_reg {
+            None => {
+                let addr_a = addr_a_fixed
+                    .ok_or(Error::Instruction("Cannot determine bus address register"))?;
+                (addr_a, const_val.unwrap_or_else(|| op_b_reg.unwrap_or(0)), false)
+            }
+            Some(dest) => match addr_a_fixed {
+                Some(base) if dest == base => {
+                    (base, const_val.unwrap_or_else(|| op_b_reg.unwrap_or(0)), false)
+                }
+                Some(base) => match op_b_reg {
+                    Some(reg_b) if reg_b == dest => (base, reg_b, true),
+                    _ => return Err(Error::Instruction(
+                        "Destination register is neither alu input a nor b")),
+                },
+                None => match op_b_reg {
+                    Some(reg_b) if reg_b == dest => (0, reg_b, true),
+                    _ => (dest, const_val.unwrap_or_else(|| op_b_reg.unwrap_or(0)), false),
+                },
+            },
+        };
+
+        if malua && bus_addr.is_some() {
+            return Err(Error::Instruction("Cannot read and write the bus at the same time"));
+        }
+        let busen = malua || bus_addr.is_some();
+        let buswr = bus_addr.is_some();
+
+        Ok((alu, write_reg.is_some(), mrgws, busen, buswr,
+            addr_a, const_or_reg_b, malua, malub))
+    }
+
+    /// Parse the alu expression (the right hand side of an assignment, or the
+    /// whole head for `TEST`) into the opcode and its operands.
+    fn parse_expr(expr: &str, carry: Option<&str>)
+        -> Result<(u8, Option<(u8, bool)>, Option<BOperand>)> {
+        if expr == "0" {
+            return Ok((0b0011, None, None));
+        }
+
+        if let Some(inner) = expr.strip_prefix('¬') {
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((0b0010, Some(a), Some(BOperand::Reg(a.0))));
+        }
+
+        if let Some((a, b)) = expr.split_once(" NOR ") {
+            let a = Self::parse_operand_a(a)?;
+            let b = Self::parse_operand_b(b)?;
+            return Ok((0b0010, Some(a), Some(b)));
+        }
+
+        if let Some(inner) = expr.strip_prefix('(').and_then(|s| s.strip_suffix(") + 1")) {
+            let a = Self::parse_operand_a(inner.strip_suffix(" << 1")
+                .ok_or(Error::Instruction("Expected '(Rn << 1) + 1'"))?)?;
+            return Ok((0b0101, Some(a), Some(BOperand::Reg(a.0))));
+        }
+        if let Some(inner) = expr.strip_prefix('(').and_then(|s| s.strip_suffix(") + C")) {
+            let a = Self::parse_operand_a(inner.strip_suffix(" << 1")
+                .ok_or(Error::Instruction("Expected '(Rn << 1) + C'"))?)?;
+            return Ok((0b0110, Some(a), Some(BOperand::Reg(a.0))));
+        }
+        if let Some(inner) = expr.strip_prefix('(').and_then(|s| s.strip_suffix(") + ¬C")) {
+            let a = Self::parse_operand_a(inner.strip_suffix(" << 1")
+                .ok_or(Error::Instruction("Expected '(Rn << 1) + ¬C'"))?)?;
+            return Ok((0b0111, Some(a), Some(BOperand::Reg(a.0))));
+        }
+
+        if let Some((a, b)) = expr.split_once(" + ") {
+            if let Some(b) = b.strip_suffix(" + 1") {
+                let a = Self::parse_operand_a(a)?;
+                let b = Self::parse_operand_b(b)?;
+                return Ok((0b0101, Some(a), Some(b)));
+            }
+            if let Some(b) = b.strip_suffix(" + C") {
+                let a = Self::parse_operand_a(a)?;
+                let b = Self::parse_operand_b(b)?;
+                return Ok((0b0110, Some(a), Some(b)));
+            }
+            if let Some(b) = b.strip_suffix(" + ¬C") {
+                let a = Self::parse_operand_a(a)?;
+                let b = Self::parse_operand_b(b)?;
+                return Ok((0b0111, Some(a), Some(b)));
+            }
+
+            let alu = if carry == Some("HLDC") { 0b0000 } else { 0b0100 };
+            let a = Self::parse_operand_a(a)?;
+            let b = Self::parse_operand_b(b)?;
+            return Ok((alu, Some(a), Some(b)));
+        }
+
+        if let Some(inner) = expr.strip_suffix(" << 1") {
+            let alu = if carry == Some("HLDC") { 0b0000 } else { 0b0100 };
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((alu, Some(a), Some(BOperand::Reg(a.0))));
+        }
+
+        // The shift/rotate ops only ever read `a`; alu input b is unused but
+        // still has to be filled in, so it defaults to the all-ones constant
+        // (matching the convention used throughout this crate's fixtures).
+        if let Some(inner) = expr.strip_suffix(" >> 1") {
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((0b1000, Some(a), Some(BOperand::Const(0xFF))));
+        }
+        if let Some(inner) = expr.strip_suffix(" R> 1") {
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((0b1001, Some(a), Some(BOperand::Const(0xFF))));
+        }
+        if let Some(inner) = expr.strip_suffix(" C> 1") {
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((0b1010, Some(a), Some(BOperand::Const(0xFF))));
+        }
+        if let Some(inner) = expr.strip_suffix(" ?> 1") {
+            let a = Self::parse_operand_a(inner)?;
+            return Ok((0b1011, Some(a), Some(BOperand::Const(0xFF))));
+        }
+
+        // A lone operand: either `a` (0001, only ever a register, optionally
+        // bus-read) or `b` (1100-1111, register or constant, never bus-read).
+        match carry {
+            Some("SETC") => Ok((0b1101, None, Some(Self::parse_operand_b(expr)?))),
+            Some("HLDC") => Ok((0b1110, None, Some(Self::parse_operand_b(expr)?))),
+            Some("INVC") => Ok((0b1111, None, Some(Self::parse_operand_b(expr)?))),
+            Some(_) => Err(Error::Instruction("Unexpected modifier for a lone operand")),
+            None if expr.starts_with('(') => {
+                let a = Self::parse_operand_a(expr)?;
+                Ok((0b0001, Some(a), None))
+            }
+            // Ambiguous between 0001 (a) and 1100 (b) for a bare register;
+            // default to the simpler pass-through-b encoding.
+            None => Ok((0b1100, None, Some(Self::parse_operand_b(expr)?))),
+        }
+    }
+
+    /// Resolve the address control modifier (everything describing the next
+    /// instruction address) into `(mac, na)`.
+    fn parse_address_control(modifier: Option<&str>, address: Option<usize>) -> Result<(u8, u8)> {
+        match modifier {
+            None => {
+                let address = address
+                    .ok_or(Error::Instruction("Implicit next address needs the current address"))?;
+                Ok((0b00, (address + 1) as u8 & 0b11111))
+            }
+            Some("LOOP") => {
+                let address = address
+                    .ok_or(Error::Instruction("LOOP needs the current address"))?;
+                Ok((0b00, address as u8 & 0b11111))
+            }
+            Some(modifier) => {
+                let mut words = modifier.split_whitespace();
+                let label = words.next()
+                    .ok_or(Error::Instruction("Empty address control modifier"))?;
+                let coded = words.next()
+                    .ok_or(Error::Instruction("Address control modifier is missing its address"))?;
+
+                if label == "JMP" {
+                    let na = u8::from_str_radix(coded, 2)
+                        .map_err(|_| Error::Instruction("Invalid binary address after JMP"))?;
+                    return Ok((0b00, na));
+                }
+
+                let full = match label {
+                    "INTA" => 0b010,
+                    "CF" => 0b011,
+                    "CO" => 0b100,
+                    "ZO" => 0b101,
+                    "NO" => 0b110,
+                    "INTB" => 0b111,
+                    _ => return Err(Error::Instruction("Unknown address control modifier")),
+                };
+
+                let na_base = coded.get(..coded.len().saturating_sub(1))
+                    .ok_or(Error::Instruction("Address control modifier is missing its letter"))?;
+                let na_base = u8::from_str_radix(na_base, 2)
+                    .map_err(|_| Error::Instruction("Invalid binary address in modifier"))?;
+
+                Ok((full >> 1, na_base << 1 | (full & 0b1)))
+            }
+        }
+    }
+
+    /// Parse a `Rn` or `(Rn)` operand into its register and whether it is
+    /// read through the bus.
+    fn parse_operand_a(s: &str) -> Result<(u8, bool)> {
+        if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            Ok((Self::parse_register(inner)?, true))
+        } else {
+            Ok((Self::parse_register(s)?, false))
+        }
+    }
+
+    /// Parse a `Rn` or hex constant operand.
+    fn parse_operand_b(s: &str) -> Result<BOperand> {
+        if let Ok(reg) = Self::parse_register(s) {
+            Ok(BOperand::Reg(reg))
+        } else {
+            u8::from_str_radix(s, 16)
+                .map(BOperand::Const)
+                .map_err(|_| Error::Instruction("Expected a register or a hex constant"))
+        }
+    }
+
+    /// Parse a `Rn` register name (`n` between 0 and 7).
+    fn parse_register(s: &str) -> Result<u8> {
+        let n = s.strip_prefix('R')
+            .ok_or(Error::Instruction("Expected a register name like 'R0'"))?;
+        let n: u8 = n.parse().map_err(|_| Error::Instruction("Invalid register number"))?;
+        if n < 8 {
+            Ok(n)
+        } else {
+            Err(Error::Instruction("Register number out of range (0-7)"))
+        }
+    }
+}
+
+/// The constant/register source for alu input b, used while assembling.
+#[derive(Clone, Copy, PartialEq)]
+enum BOperand {
+    Reg(u8),
+    Const(u8),
 }
 
 impl fmt::Debug for Instruction {
@@ -400,4 +758,69 @@ mod tests {
             assert_eq!(Instruction::new(i).unwrap().to_mnemonic(na), s.to_string());
         }
     }
+
+    #[test]
+    fn from_mnemonic_round_trip() {
+        // Same testcases as `to_string`, assembled back into the original word.
+        let testcases = [
+            (0b00_00001_00_000_0000_00_00_0000_0, "NOP", Some(0)),
+            (0b00_00011_00_000_0000_00_00_0000_0, "NOP", Some(2)),
+            (0b00_00000_00_000_0000_00_00_0000_0, "NOP; LOOP", Some(0)),
+            (0b00_00010_00_000_0000_00_00_0000_0, "NOP; LOOP", Some(2)),
+            (0b00_00000_00_000_0000_00_00_0000_0, "NOP; JMP 00000", None),
+            (0b01_00000_00_000_0000_00_00_0000_0, "NOP; INTA 0000I", None),
+            (0b11_00001_00_000_0000_00_00_0000_0, "NOP; INTB 0000I", None),
+            (0b00_00001_00_000_0000_00_00_0000_1, "NOP; CHFL", Some(0)),
+            (0b00_00001_00_000_1111_01_01_0000_0, "R0 = R0 + FF; HLDC", Some(0)),
+            (0b00_00001_00_000_0001_01_00_0000_0, "R0 = R0 + R1; HLDC", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0000_0, "R0 = R0 << 1; HLDC", Some(0)),
+            (0b00_00001_00_000_0000_00_00_0001_0, "TEST R0", Some(0)),
+            (0b00_00001_01_000_0000_01_10_0001_0, "R0 = (R0)", Some(0)),
+            (0b01_00010_00_000_0000_00_00_0001_0, "TEST R0; INTA 0001I", None),
+            (0b01_00101_00_000_0000_00_00_0001_0, "TEST R0; CF 0010C", None),
+            (0b10_00110_00_000_0000_00_00_0001_0, "TEST R0; CO 0011C", None),
+            (0b10_01001_00_000_0000_00_00_0001_0, "TEST R0; ZO 0100Z", None),
+            (0b11_01010_00_000_0000_00_00_0001_0, "TEST R0; NO 0101N", None),
+            (0b11_01101_00_000_0000_00_00_0001_0, "TEST R0; INTB 0110I", None),
+            (0b00_00001_00_000_1111_01_01_0010_0, "R0 = R0 NOR FF", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0010_0, "R0 = ¬R0", Some(0)),
+            (0b00_00001_00_010_0000_01_00_0011_0, "R2 = 0", Some(0)),
+            (0b00_00001_00_000_1111_01_01_0100_0, "R0 = R0 + FF", Some(0)),
+            (0b00_00001_00_000_0001_01_00_0100_0, "R0 = R0 + R1", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0100_0, "R0 = R0 << 1", Some(0)),
+            (0b00_00001_00_000_1111_01_01_0101_0, "R0 = R0 + FF + 1", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0101_0, "R0 = (R0 << 1) + 1", Some(0)),
+            (0b00_00001_00_000_1111_01_01_0110_0, "R0 = R0 + FF + C", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0110_0, "R0 = (R0 << 1) + C", Some(0)),
+            (0b00_00001_00_000_1111_01_01_0111_0, "R0 = R0 + FF + ¬C", Some(0)),
+            (0b00_00001_00_000_0000_01_00_0111_0, "R0 = (R0 << 1) + ¬C", Some(0)),
+            (0b00_00001_00_000_1111_01_01_1000_0, "R0 = R0 >> 1", Some(0)),
+            (0b00_00001_00_000_1111_01_01_1001_0, "R0 = R0 R> 1", Some(0)),
+            (0b00_00001_00_000_1111_01_01_1010_0, "R0 = R0 C> 1", Some(0)),
+            (0b00_00001_00_000_1111_01_01_1011_0, "R0 = R0 ?> 1", Some(0)),
+            (0b00_00001_00_000_1100_01_01_1100_0, "R0 = FC", Some(0)),
+            (0b00_00000_00_000_1100_01_01_1100_0, "R0 = FC; JMP 00000", None),
+            (0b00_00000_00_000_1100_01_01_1100_0, "R0 = FC; LOOP", Some(0)),
+            (0b00_00000_00_000_1100_01_01_1100_0, "R0 = FC; JMP 00000", Some(1)),
+            (0b00_00001_11_001_0010_00_00_1100_0, "(R1) = R2", Some(0)),
+            (0b00_00001_11_001_0011_01_01_1100_0, "(R1),R1 = 3", Some(0)),
+            (0b00_00001_00_000_1100_01_01_1101_0, "R0 = FC; SETC", Some(0)),
+            (0b00_00001_00_000_1100_01_01_1110_0, "R0 = FC; HLDC", Some(0)),
+            (0b00_00001_00_000_1100_01_01_1111_0, "R0 = FC; INVC", Some(0)),
+            (0b00_00000_00_000_1100_01_01_1111_1, "R0 = FC; INVC; JMP 00000; CHFL", None),
+        ];
+
+        for &(i, s, na) in testcases.iter() {
+            assert_eq!(Instruction::from_mnemonic(s, na).unwrap(), Instruction::new(i).unwrap());
+        }
+    }
+
+    #[test]
+    fn from_mnemonic_errors() {
+        // Neither operand register matches the destination.
+        assert!(Instruction::from_mnemonic("R0 = R1 + R2", Some(0)).is_err());
+        // Implicit next address needs a current address to resolve against.
+        assert!(Instruction::from_mnemonic("NOP", None).is_err());
+        assert!(Instruction::from_mnemonic("garbage", Some(0)).is_err());
+    }
 }
@@ -0,0 +1,153 @@
+//! Interrupt controller for the 2i's INTA/INTB next-address sources.
+//!
+//! The `Instruction` decoder already recognizes `INTA` (`get_full_address_control`
+//! `== 0b010`) and `INTB` (`== 0b111`) as interrupt-driven next-address
+//! sources, but resolving them requires knowing whether the corresponding
+//! request line is currently pending. This module provides that state.
+
+/// One of the two next-address sources driven by an external interrupt: the
+/// volatile interrupt (MAC 010), which only applies to the single
+/// instruction executed while it is pending, and the stored interrupt
+/// (MAC 111), which stays pending until an instruction with MAC 111 actually
+/// consumes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptLine {
+    A,
+    B,
+}
+
+/// Holds the pending state of the two interrupt request lines and resolves
+/// them into the NA0 bit used while calculating the next microinstruction
+/// address.
+#[derive(Debug, Default)]
+pub struct InterruptController {
+    pending_a: bool,
+    pending_b: bool,
+    vector_a: Option<&'static str>,
+    vector_b: Option<&'static str>,
+}
+
+impl InterruptController {
+    /// Create a new interrupt controller with both lines deasserted.
+    pub fn new() -> InterruptController {
+        InterruptController::default()
+    }
+
+    /// Assert the given interrupt request line, optionally registering the
+    /// name of the handler/peripheral that raised it, so it can be
+    /// inspected later (eg. by a debugger UI).
+    pub fn assert(&mut self, line: InterruptLine, vector: Option<&'static str>) {
+        match line {
+            InterruptLine::A => {
+                self.pending_a = true;
+                self.vector_a = vector;
+            }
+            InterruptLine::B => {
+                self.pending_b = true;
+                self.vector_b = vector;
+            }
+        }
+    }
+
+    /// Deassert the given interrupt request line without it ever being used
+    /// to steer a next address.
+    pub fn deassert(&mut self, line: InterruptLine) {
+        match line {
+            InterruptLine::A => {
+                self.pending_a = false;
+                self.vector_a = None;
+            }
+            InterruptLine::B => {
+                self.pending_b = false;
+                self.vector_b = None;
+            }
+        }
+    }
+
+    /// Check whether the given line is currently pending.
+    pub fn is_pending(&self, line: InterruptLine) -> bool {
+        match line {
+            InterruptLine::A => self.pending_a,
+            InterruptLine::B => self.pending_b,
+        }
+    }
+
+    /// Name of the handler/peripheral that last asserted the given line, if
+    /// one was registered.
+    pub fn vector(&self, line: InterruptLine) -> Option<&'static str> {
+        match line {
+            InterruptLine::A => self.vector_a,
+            InterruptLine::B => self.vector_b,
+        }
+    }
+
+    /// Resolve the given line into the NA0 bit that gets or'd onto `base`
+    /// (the next address with its last bit already masked off), steering
+    /// execution to the `xxxxI` interrupt vector while the line is pending
+    /// and falling through to the base address otherwise.
+    pub fn resolve(&self, line: InterruptLine, base: u8) -> u8 {
+        base | self.is_pending(line) as u8
+    }
+
+    /// Acknowledge (clear) the volatile interrupt. Called unconditionally
+    /// after every instruction, since MAC 010 only ever applies to a single
+    /// instruction.
+    pub fn acknowledge_volatile(&mut self) {
+        self.deassert(InterruptLine::A);
+    }
+
+    /// Acknowledge (clear) the stored interrupt. Called only once an
+    /// instruction with MAC 111 and NA0 = 1 actually consumes it.
+    pub fn acknowledge_stored(&mut self) {
+        self.deassert(InterruptLine::B);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_line_steers_to_interrupt_vector() {
+        let mut controller = InterruptController::new();
+        controller.assert(InterruptLine::A, None);
+        controller.assert(InterruptLine::B, None);
+
+        assert_eq!(controller.resolve(InterruptLine::A, 0b11110), 0b11111);
+        assert_eq!(controller.resolve(InterruptLine::B, 0b11110), 0b11111);
+    }
+
+    #[test]
+    fn clear_line_falls_through_to_base_address() {
+        let controller = InterruptController::new();
+
+        assert_eq!(controller.resolve(InterruptLine::A, 0b11110), 0b11110);
+        assert_eq!(controller.resolve(InterruptLine::B, 0b11110), 0b11110);
+    }
+
+    #[test]
+    fn deassert_clears_a_pending_line_and_its_vector() {
+        let mut controller = InterruptController::new();
+        controller.assert(InterruptLine::A, Some("timer"));
+        assert!(controller.is_pending(InterruptLine::A));
+        assert_eq!(controller.vector(InterruptLine::A), Some("timer"));
+
+        controller.deassert(InterruptLine::A);
+        assert!(!controller.is_pending(InterruptLine::A));
+        assert_eq!(controller.vector(InterruptLine::A), None);
+    }
+
+    #[test]
+    fn acknowledging_one_line_does_not_affect_the_other() {
+        let mut controller = InterruptController::new();
+        controller.assert(InterruptLine::A, None);
+        controller.assert(InterruptLine::B, None);
+
+        controller.acknowledge_volatile();
+        assert!(!controller.is_pending(InterruptLine::A));
+        assert!(controller.is_pending(InterruptLine::B));
+
+        controller.acknowledge_stored();
+        assert!(!controller.is_pending(InterruptLine::B));
+    }
+}
@@ -4,43 +4,67 @@
 //! programmed using microcode and is used in the computer science hardware
 //! course at Leipzig University.
 
+use std::error;
 use std::fmt;
 use std::io;
 use std::result;
 
 pub mod alu;
 pub mod bus;
+pub mod cfg;
 pub mod cpu;
+pub mod device;
+pub mod diagnostics;
 pub mod instruction;
+pub mod interrupt;
 pub mod parse;
+pub mod variant;
 
 // Re-exports
 pub use crate::alu::Flags;
 pub use crate::cpu::Cpu;
+pub use crate::device::Timer;
 pub use crate::instruction::Instruction;
-pub use crate::bus::{Bus, IoRegisters, Ram};
+pub use crate::interrupt::{InterruptController, InterruptLine};
+pub use crate::variant::{AddressSource, Reduced, Standard, Variant};
+pub use crate::bus::{Bus, BusAccess, IoRegisters, Ram};
 
 #[derive(Debug)]
 pub enum Error {
-    Bus(&'static str),
+    /// A bus access failed at the given address (eg. an out of range read or
+    /// a write to a read-only register).
+    Bus(u8, &'static str),
     Cpu(&'static str),
     Instruction(&'static str),
     Parse(&'static str),
+    /// A parse error with an annotated source position, used where a
+    /// precise line/column is available (eg. while reading a `.2i` program).
+    Diagnostic(diagnostics::Diagnostic),
     Io(io::Error),
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            &Error::Bus(s) => write!(f, "Bus error: {}", s),
+            &Error::Bus(addr, s) => write!(f, "Bus error at {:#04x}: {}", addr, s),
             &Error::Cpu(s) => write!(f, "Cpu error: {}", s),
             &Error::Instruction(s) => write!(f, "Instruction error: {}", s),
             &Error::Parse(s) => write!(f, "Parse error: {}", s),
+            &Error::Diagnostic(ref d) => write!(f, "{}", d),
             &Error::Io(ref s) => write!(f, "IO error: {}", s),
         }
     }
 }
 
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            &Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
         Error::Io(error)
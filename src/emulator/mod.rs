@@ -5,10 +5,12 @@ pub mod alu;
 pub mod bus;
 pub mod cpu;
 pub mod instruction;
+pub mod interrupt;
+pub mod variant;
 
 #[derive(Debug)]
 pub enum Error {
-    Bus(&'static str),
+    Bus(u8, &'static str),
     Cpu(&'static str),
     Instruction(&'static str),
 }
@@ -16,7 +18,7 @@ pub enum Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &Error::Bus(s) => write!(f, "Bus error: {}", s),
+            &Error::Bus(addr, s) => write!(f, "Bus error at {:#04x}: {}", addr, s),
             &Error::Cpu(s) => write!(f, "Cpu error: {}", s),
             &Error::Instruction(s) => write!(f, "Instruction error: {}", s),
         }
@@ -2,23 +2,35 @@
 //!
 //! This module contains functions for parsing 2i programs.
 
+use std::collections::HashMap;
 use std::io::BufReader;
 use std::io::prelude::*;
 
-use regex::Regex;
+use regex::{Captures, Match, Regex};
 
 use super::{Error, Result};
+use super::diagnostics::{Diagnostic, Span};
 use super::instruction::Instruction;
 
+/// Separator characters that are only used to visually group the bits of an
+/// instruction and are otherwise ignored. Anything else that is not `0` or
+/// `1` results in a `Diagnostic` pointing at the offending character.
+const IGNORED_CHARS: &[char] = &[' ', '\t', '|', ',', '_'];
+
 /// Parse 2i programs in string representation into arrays of `Instruction`s.
 ///
-/// Ignores empty lines and everything after the `#` char. You can use any char
-/// other than `0`, `1` and `:` to format your program for improved readability.
+/// Ignores empty lines and everything after the `#` char. You can use
+/// whitespace, `|`, `,` and `_` to format your program for improved
+/// readability.
 ///
 /// Instructions can optionally be given an explicit address by prefixing them
 /// with the binary representation of the address followed by `:`. Instructions
 /// without an explicit address are saved at the first unused address.
 ///
+/// Malformed lines (eg. a non-binary character where a bit is expected, or an
+/// instruction wider than 25 bits) are reported as a `Diagnostic` that points
+/// at the exact character responsible.
+///
 /// # Examples
 ///
 /// ```text
@@ -41,15 +53,188 @@ pub fn read_program<R: Read>(reader: R) -> Result<[Instruction; 32]> {
     Ok(final_instructions)
 }
 
-/// Iterator stored on the stack with variable length and storage size of 2
-macro_rules! alternative_2 {
-    // TODO: Using a custom iterator instead of once would be more efficient
-    ($first:expr) => (
-        ::std::iter::once($first).chain(::std::iter::once($first)).take(1)
-    );
-    ($first:expr, $second:expr) => (
-        ::std::iter::once($first).chain(::std::iter::once($second)).take(2)
-    );
+/// Assemble 2i programs written in the mnemonic syntax produced by
+/// `Instruction::to_mnemonic` into arrays of `Instruction`s.
+///
+/// Ignores empty lines and everything after the `#` char. Instructions can
+/// optionally be given an explicit address by prefixing them with the binary
+/// representation of the address followed by `:`, exactly as in
+/// `read_program`. Instructions without an explicit address are saved at the
+/// first unused address and are assembled against that address, so that the
+/// implicit "fall through" and `LOOP` forms resolve correctly.
+///
+/// Instructions can also be prefixed with a named label (eg. `loop:`) instead
+/// of a binary address, either on their own line or directly before the
+/// instruction they name. `JMP <label>` then resolves to that instruction's
+/// address instead of requiring a hand-counted 5 bit address, in a first pass
+/// over the program that only assigns addresses and records labels, followed
+/// by a second pass that substitutes the recorded addresses and encodes each
+/// instruction.
+///
+/// # Examples
+///
+/// ```text
+/// # Read value from FC into register 0
+///
+///        R0 = FC
+/// loop:  R0 = (R0); JMP loop
+/// ```
+pub fn assemble_program<R: Read>(reader: R) -> Result<[Instruction; 32]> {
+    let explicit_address = Regex::new(r"^(?P<addr>[01]{5})\s*:\s*(?P<mnemonic>.*)$").unwrap();
+    let label_definition = Regex::new(r"^(?P<label>[A-Za-z_][A-Za-z0-9_]*)\s*:\s*(?P<mnemonic>.*)$").unwrap();
+
+    // Pass 1: strip comments/blanks, assign every instruction its address
+    // (honoring explicit `NNNNN:` addresses exactly as `parse_instructions`
+    // does) and record every `label:` in a symbol table pointing at the
+    // address of the instruction it names.
+    let mut lines: Vec<Option<String>> = vec![None; 32];
+    let mut labels: HashMap<String, u8> = HashMap::new();
+    let mut pending_labels: Vec<String> = Vec::new();
+
+    let reader = BufReader::new(reader);
+    for line in reader.lines() {
+        let line = line?;
+
+        // Remove whitespace and comments that start with #
+        let line = match line.find('#') {
+            Some(start) => line[..start].trim(),
+            None => line.trim(),
+        };
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(matches) = explicit_address.captures(line) {
+            let mnemonic = matches.name("mnemonic").unwrap().as_str();
+            let address = u8::from_str_radix(matches.name("addr").unwrap().as_str(), 2).unwrap() as usize;
+
+            bind_instruction(&mut lines, &mut labels, &mut pending_labels, address, mnemonic)?;
+        } else if let Some(matches) = label_definition.captures(line) {
+            let label = matches.name("label").unwrap().as_str().to_string();
+            let mnemonic = matches.name("mnemonic").unwrap().as_str();
+
+            if labels.contains_key(&label) || pending_labels.contains(&label) {
+                return Err(Error::Parse("Duplicate label definition"));
+            }
+
+            if mnemonic.is_empty() {
+                // A standalone label line: bind it once the next instruction
+                // claims an address.
+                pending_labels.push(label);
+            } else {
+                let address = lines.iter().position(|i| i.is_none())
+                    .ok_or(Error::Parse("Too many instructions in this program"))?;
+                labels.insert(label, address as u8);
+                bind_instruction(&mut lines, &mut labels, &mut pending_labels, address, mnemonic)?;
+            }
+        } else {
+            let address = lines.iter().position(|i| i.is_none())
+                .ok_or(Error::Parse("Too many instructions in this program"))?;
+            bind_instruction(&mut lines, &mut labels, &mut pending_labels, address, line)?;
+        }
+    }
+
+    if !pending_labels.is_empty() {
+        return Err(Error::Parse("Label at the end of the program has no instruction to name"));
+    }
+
+    // Pass 2: substitute every `JMP <label>` with the recorded address and
+    // encode the resulting raw mnemonic.
+    let jump_target = Regex::new(r"\bJMP\s+(?P<label>[A-Za-z_][A-Za-z0-9_]*)\b").unwrap();
+
+    let mut instructions = [Instruction::default(); 32];
+    for (address, mnemonic) in lines.iter().enumerate() {
+        let mnemonic = match mnemonic {
+            Some(mnemonic) => mnemonic,
+            None => continue,
+        };
+
+        for captures in jump_target.captures_iter(mnemonic) {
+            if !labels.contains_key(&captures["label"]) {
+                return Err(Error::Parse("Reference to undefined label"));
+            }
+        }
+
+        let resolved = jump_target.replace_all(mnemonic, |captures: &Captures| {
+            format!("JMP {:05b}", labels[&captures["label"]])
+        });
+
+        instructions[address] = Instruction::from_mnemonic(&resolved, Some(address))?;
+    }
+
+    Ok(instructions)
+}
+
+/// Record that `mnemonic` claims `address`, binding any labels still waiting
+/// for their instruction's address to it first.
+fn bind_instruction(lines: &mut [Option<String>], labels: &mut HashMap<String, u8>,
+                     pending_labels: &mut Vec<String>, address: usize, mnemonic: &str) -> Result<()> {
+    if address >= 32 {
+        return Err(Error::Parse("Label resolves to an address above 31"));
+    }
+    if lines[address].is_some() {
+        return Err(Error::Parse("Two instructions with the same address"));
+    }
+
+    for label in pending_labels.drain(..) {
+        labels.insert(label, address as u8);
+    }
+    lines[address] = Some(mnemonic.to_string());
+
+    Ok(())
+}
+
+/// Magic bytes identifying a 2i object file, written at the very start of
+/// every file `write_program_binary` produces.
+const BINARY_MAGIC: &[u8; 4] = b"2iOB";
+
+/// The binary format's version, bumped whenever the format's layout changes
+/// incompatibly. `read_program_binary` rejects any other version.
+const BINARY_VERSION: u8 = 1;
+
+/// Serialize `program` into a compact little-endian binary blob: `BINARY_MAGIC`,
+/// then `BINARY_VERSION`, then each of the 32 instructions as a 4 byte
+/// little-endian word (`Instruction::get_instruction`), so assembled
+/// programs can be distributed and reloaded without re-parsing their source.
+pub fn write_program_binary<W: Write>(program: &[Instruction; 32], mut writer: W) -> Result<()> {
+    writer.write_all(BINARY_MAGIC)?;
+    writer.write_all(&[BINARY_VERSION])?;
+
+    for instruction in program.iter() {
+        writer.write_all(&instruction.get_instruction().to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Read back a program written by `write_program_binary`.
+///
+/// Rejects a missing/mismatched `BINARY_MAGIC`, an unsupported
+/// `BINARY_VERSION`, and any instruction word that fails `Instruction::new`
+/// (eg. one wider than 25 bits), all as `Error::Parse`.
+pub fn read_program_binary<R: Read>(mut reader: R) -> Result<[Instruction; 32]> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != BINARY_MAGIC {
+        return Err(Error::Parse("Not a 2i object file (bad magic bytes)"));
+    }
+
+    let mut version = [0u8; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != BINARY_VERSION {
+        return Err(Error::Parse("Unsupported 2i object file version"));
+    }
+
+    let mut instructions = [Instruction::default(); 32];
+    for slot in instructions.iter_mut() {
+        let mut word = [0u8; 4];
+        reader.read_exact(&mut word)?;
+        *slot = Instruction::new(u32::from_le_bytes(word))
+            .map_err(|_| Error::Parse("Instruction word is wider than 25 bits"))?;
+    }
+
+    Ok(instructions)
 }
 
 /// Parse 2i programs in string representation and return only the reachable
@@ -57,88 +242,48 @@ macro_rules! alternative_2 {
 ///
 /// Instructions are considered reachable if there is a chain of instructions
 /// starting from the first one at address 0 to it. This also considers
-/// conditional jumps.
+/// conditional jumps. Built on top of `cfg::build_cfg`; see that module for
+/// an explicit graph representation of the same traversal.
 ///
 /// For details on the syntax of the string representation see `read_program`.
 pub fn read_reachable_program<R: Read>(reader: R) -> Result<Vec<(u8, Instruction)>> {
-    #[derive(Clone, Copy)]
-    enum S {
-        Empty, // Not yet visited
-        Visited, // Visited, but instruction is missing (will get default one)
-        Instruction(Instruction), // Visited and containing a instruction
-    }
-
     let instructions = parse_instructions(reader)?;
-    let mut reachable_instructions = [S::Empty; 32];
 
-    // The instruction at address 0 is reachable by definition if it exists
-    reachable_instructions[0] = if let Some(inst) = instructions[0] {
-        S::Instruction(inst)
-    } else {
+    if instructions[0].is_none() {
         return Err(Error::Parse("No instruction reachable"));
-    };
-
-    // Since instructions can jump to earlier addresses, we have to iterate
-    // until no new instruction is found.
-    let mut finished = false;
-    while !finished {
-        finished = true;
-
-        for i in 0..reachable_instructions.len() {
-            if let S::Instruction(inst) = reachable_instructions[i] {
-                let na = inst.get_next_instruction_address();
-
-                // Consider both target addresses for conditional jumps
-                let target_addresses = if inst.get_address_control() == 0 {
-                    alternative_2!(na)
-                } else {
-                    alternative_2!(na & !1u8, na | 1u8)
-                };
-
-                for addr in target_addresses {
-                    let addr = addr as usize;
-                    // Only update instruction addresses that were not yet
-                    // visited. This ensures that the algorithm terminates
-                    if let S::Empty = reachable_instructions[addr] {
-                        finished = false;
-                        if let Some(inst) = instructions[addr] {
-                            reachable_instructions[addr] = S::Instruction(inst);
-                        } else {
-                            reachable_instructions[addr] = S::Visited;
-                        }
-                    }
-                }
-            }
-        }
     }
 
+    let cfg = super::cfg::build_cfg(&instructions);
+
     // Addresses which were visited but did not have a valid instruction get
     // a default one (NOP, JMP 0)
-    Ok(reachable_instructions.iter().enumerate().filter_map(|(i,inst)| {
-        match *inst {
-            S::Empty => None,
-            S::Visited => Some((i as u8, Instruction::default())),
-            S::Instruction(inst) => Some((i as u8, inst)),
-        }
-    }).collect())
+    Ok(cfg.nodes.iter().map(|node| (node.address, node.instruction.unwrap_or_default())).collect())
 }
 
 /// Actually parse the instructions from the given reader
 ///
+/// Every parse failure, including a malformed address and a clash or overflow
+/// while assigning one, is reported as an `Error::Diagnostic` pointing at the
+/// offending address or instruction text, not just a bare message.
+///
 /// For details on the syntax of the string representation see `read_program`.
-fn parse_instructions<R: Read>(reader: R) -> Result<[Option<Instruction>; 32]> {
+pub(crate) fn parse_instructions<R: Read>(reader: R) -> Result<[Option<Instruction>; 32]> {
     let mut instructions = [None; 32];
     let explicit_address = Regex::new(r"^(?P<addr>[01]{5})\s*:\s*(?P<inst>.*)$").unwrap();
 
     let reader = BufReader::new(reader);
-    for line in reader.lines() {
-        let line = line?;
+    for (line_no, line) in reader.lines().enumerate() {
+        let line_no = line_no + 1;
+        let raw_line = line?;
 
         // Remove whitespace and comments that start with #
-        let line = match line.find('#') {
-            Some(start) => line[..start].trim(),
-            None => line.trim(),
+        let content = match raw_line.find('#') {
+            Some(start) => &raw_line[..start],
+            None => &raw_line[..],
         };
+        let without_leading_ws = content.trim_start();
+        let leading_ws = content.len() - without_leading_ws.len();
+        let line = without_leading_ws.trim_end();
 
         // Ignore empty lines
         if line.is_empty() {
@@ -146,41 +291,63 @@ fn parse_instructions<R: Read>(reader: R) -> Result<[Option<Instruction>; 32]> {
         }
 
         // Check if an explicit address is given
-        let (instruction, address) = if line.contains(':') {
+        let (instruction, address, inst_col): (&str, Option<Match>, usize) = if line.contains(':') {
             match explicit_address.captures(line) {
                 Some(matches) => {
-                    let inst = matches.name("inst").unwrap().as_str();
-                    let addr = matches.name("addr").unwrap().as_str();
-                    (inst, Some(addr))
+                    let inst = matches.name("inst").unwrap();
+                    let addr = matches.name("addr").unwrap();
+                    (inst.as_str(), Some(addr), leading_ws + inst.start())
+                }
+                None => {
+                    return Err(Error::Diagnostic(Diagnostic::new(
+                        Span::new(line_no, leading_ws + 1, line.len()),
+                        "Invalid instruction address",
+                        &raw_line,
+                    )));
                 }
-                None => return Err(Error::Parse("Invalid instruction address")),
             }
         } else {
-            (line, None)
+            (line, None, leading_ws)
         };
 
+        validate_instruction_chars(instruction, line_no, inst_col, &raw_line)?;
+        let instruction_len = instruction.len();
+
         // Parse Instruction
         let raw_inst = convert_binary_string_to_int(&instruction);
         let instruction = try!(Instruction::new(raw_inst));
 
         if let Some(address) = address {
             // Parse specified address
-            let address = convert_binary_string_to_int(&address) as usize;
+            let addr_col = leading_ws + address.start();
+            let address = convert_binary_string_to_int(address.as_str()) as usize;
             if address >= 32 {
-                return Err(Error::Parse("Specified instruction address too big"));
+                return Err(Error::Diagnostic(Diagnostic::new(
+                    Span::new(line_no, addr_col + 1, 5),
+                    "Specified instruction address too big",
+                    &raw_line,
+                )));
             }
 
             if instructions[address].is_none() {
                 instructions[address] = Some(instruction);
             } else {
-                return Err(Error::Parse("Two instructions with the same address"));
+                return Err(Error::Diagnostic(Diagnostic::new(
+                    Span::new(line_no, addr_col + 1, 5),
+                    "Two instructions with the same address",
+                    &raw_line,
+                )));
             }
         } else {
             // Find the next free address
             if let Some(address) = instructions.iter().position(|i| i.is_none()) {
                 instructions[address] = Some(instruction);
             } else {
-                return Err(Error::Parse("Too many instructions in this program"));
+                return Err(Error::Diagnostic(Diagnostic::new(
+                    Span::new(line_no, inst_col + 1, instruction_len.max(1)),
+                    "Too many instructions in this program",
+                    &raw_line,
+                )));
             }
         }
     }
@@ -188,6 +355,41 @@ fn parse_instructions<R: Read>(reader: R) -> Result<[Option<Instruction>; 32]> {
     Ok(instructions)
 }
 
+/// Check that `text` only consists of `0`, `1` and `IGNORED_CHARS`, and that
+/// it describes an instruction of at most 25 bits, returning a `Diagnostic`
+/// pointing at the offending character otherwise.
+///
+/// `line_no` and `col` locate `text` within `full_line`, the unmodified
+/// source line that gets rendered as part of the diagnostic.
+fn validate_instruction_chars(text: &str, line_no: usize, col: usize, full_line: &str) -> Result<()> {
+    let mut bits = 0;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '0' | '1' => {
+                bits += 1;
+                if bits > 25 {
+                    return Err(Error::Diagnostic(Diagnostic::new(
+                        Span::new(line_no, col + i + 1, 1),
+                        "instruction is wider than the 25 bits of a microinstruction",
+                        full_line,
+                    )));
+                }
+            }
+            c if IGNORED_CHARS.contains(&c) => (),
+            c => {
+                return Err(Error::Diagnostic(Diagnostic::new(
+                    Span::new(line_no, col + i + 1, 1),
+                    format!("expected '0' or '1', found '{}'", c),
+                    full_line,
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Convert a binary string to a u32 ignoring any chars other than 0 and 1
 ///
 /// If the string contains more than 32 valid bits, the excess bits at the
@@ -243,6 +445,44 @@ mod tests {
         ".to_owned())).unwrap();
     }
 
+    #[test]
+    fn invalid_address_points_at_the_offending_line() {
+        let err = parse_instructions(Cursor::new("\
+            00000: 00 00001 000000000000000000\n\
+            0 0 0 0 0: 00 00001 000000000000000000\n\
+        ".to_owned())).unwrap_err();
+
+        match err {
+            Error::Diagnostic(d) => assert_eq!(d.span, Span::new(2, 1, 38)),
+            err => panic!("expected a Diagnostic, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn address_too_big_points_at_the_address() {
+        let err = parse_instructions(Cursor::new("\
+            11111: 00 00001 000000000000000000\n\
+        ".to_owned())).unwrap_err();
+
+        match err {
+            Error::Diagnostic(d) => assert_eq!(d.span, Span::new(1, 1, 5)),
+            err => panic!("expected a Diagnostic, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn duplicate_address_points_at_the_second_occurrence() {
+        let err = parse_instructions(Cursor::new("\
+            00000: 00 00001 000000000000000000\n\
+            00000: 00 00011 000000000000000000\n\
+        ".to_owned())).unwrap_err();
+
+        match err {
+            Error::Diagnostic(d) => assert_eq!(d.span, Span::new(2, 1, 5)),
+            err => panic!("expected a Diagnostic, got {:?}", err),
+        }
+    }
+
     #[test]
     fn reachable_backjump() {
         let program = Cursor::new("\
@@ -281,4 +521,120 @@ mod tests {
         let program = Cursor::new("".to_owned());
         read_reachable_program(program).unwrap();
     }
+
+    #[test]
+    fn assembler() {
+        let program = assemble_program(Cursor::new("\
+            # Load FC into R0, then loop reading the memory location it points to\n\
+            R0 = FC\n\
+            00001: R0 = (R0); LOOP\n\
+        ".to_owned())).unwrap();
+
+        assert_eq!(&program[0..2], &[
+            Instruction::new(0b00_00001_00_000_1100_01_01_1100_0).unwrap(),
+            Instruction::new(0b00_00001_01_000_0000_01_10_0001_0).unwrap(),
+        ]);
+        assert_eq!(&program[2..], &[Instruction::default(); 30]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Two instructions with the same address")]
+    fn assembler_duplicate_address() {
+        let _ = assemble_program(Cursor::new("\
+            00000: R0 = FC\n\
+            00000: R0 = FD\n\
+        ".to_owned())).unwrap();
+    }
+
+    #[test]
+    fn assembler_label_on_its_own_line() {
+        let program = assemble_program(Cursor::new("\
+            R0 = FC\n\
+            loop:\n\
+            R0 = (R0); JMP loop\n\
+        ".to_owned())).unwrap();
+
+        assert_eq!(&program[0..2], &[
+            Instruction::new(0b00_00001_00_000_1100_01_01_1100_0).unwrap(),
+            Instruction::new(0b00_00001_01_000_0000_01_10_0001_0).unwrap(),
+        ]);
+        assert_eq!(&program[2..], &[Instruction::default(); 30]);
+    }
+
+    #[test]
+    fn assembler_label_combined_with_instruction() {
+        let program = assemble_program(Cursor::new("\
+            R0 = FC\n\
+            loop: R0 = (R0); JMP loop\n\
+        ".to_owned())).unwrap();
+
+        assert_eq!(&program[0..2], &[
+            Instruction::new(0b00_00001_00_000_1100_01_01_1100_0).unwrap(),
+            Instruction::new(0b00_00001_01_000_0000_01_10_0001_0).unwrap(),
+        ]);
+        assert_eq!(&program[2..], &[Instruction::default(); 30]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate label definition")]
+    fn assembler_duplicate_label() {
+        let _ = assemble_program(Cursor::new("\
+            loop: R0 = FC\n\
+            loop: R0 = FD\n\
+        ".to_owned())).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Reference to undefined label")]
+    fn assembler_undefined_label() {
+        let _ = assemble_program(Cursor::new("\
+            R0 = FC; JMP nowhere\n\
+        ".to_owned())).unwrap();
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let program = assemble_program(Cursor::new("\
+            R0 = FC\n\
+            00001: R0 = (R0); LOOP\n\
+        ".to_owned())).unwrap();
+
+        let mut blob = Vec::new();
+        write_program_binary(&program, &mut blob).unwrap();
+
+        let read_back = read_program_binary(Cursor::new(blob)).unwrap();
+        assert_eq!(&read_back[..], &program[..]);
+    }
+
+    #[test]
+    #[should_panic(expected = "bad magic bytes")]
+    fn binary_rejects_wrong_magic() {
+        let mut blob = vec![b'x', b'x', b'x', b'x', BINARY_VERSION];
+        blob.extend(vec![0u8; 32 * 4]);
+
+        let _ = read_program_binary(Cursor::new(blob)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported")]
+    fn binary_rejects_wrong_version() {
+        let mut blob = BINARY_MAGIC.to_vec();
+        blob.push(BINARY_VERSION + 1);
+        blob.extend(vec![0u8; 32 * 4]);
+
+        let _ = read_program_binary(Cursor::new(blob)).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "wider than 25 bits")]
+    fn binary_rejects_oversized_instruction_word() {
+        let mut blob = BINARY_MAGIC.to_vec();
+        blob.push(BINARY_VERSION);
+        blob.extend(vec![0u8; 32 * 4]);
+        // Set bit 25 (one above the 25 bit instruction width) in the most
+        // significant byte of the first word.
+        blob[8] = 0b0000_0010;
+
+        let _ = read_program_binary(Cursor::new(blob)).unwrap();
+    }
 }
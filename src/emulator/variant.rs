@@ -0,0 +1,141 @@
+//! 2i microarchitecture variants.
+//!
+//! The 2i is taught in several lab revisions that differ in which ALU
+//! functions exist and how the carry-injection adds behave. A `Variant`
+//! supplies the opcode to operation mapping consulted by `Alu::calculate`
+//! and the address-control decode table consulted by
+//! `Cpu::calculate_next_instruction_address`, so `Cpu<V>` can be
+//! parameterized over the course revision a microprogram was written for
+//! and faults cleanly instead of silently executing a different op.
+
+use super::{Error, Result};
+use super::alu::Flags;
+
+/// Which signal supplies the NA0 override bit for a given
+/// `(address_control, NA0)` combination while calculating the next
+/// instruction address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSource {
+    /// NA0 passes through unmodified.
+    Direct,
+    VolatileInterrupt,
+    StoredCarry,
+    Carry,
+    Zero,
+    Negative,
+    StoredInterrupt,
+}
+
+/// A concrete 2i microarchitecture revision.
+///
+/// Implementations are zero-sized marker types used as `Cpu<V>`'s type
+/// parameter, so selecting a revision costs nothing at runtime.
+pub trait Variant {
+    /// Execute one ALU opcode with two operands, returning the result and
+    /// the resulting flags, or an `Error::Cpu` if this revision doesn't
+    /// implement the given opcode. Implementations should still panic on
+    /// genuinely invalid opcodes (ie. ones outside the 4 bit field), since
+    /// those can never occur for a correctly decoded `Instruction`.
+    fn alu_calculate(instruction: u8, a: u8, b: u8, carry: bool) -> Result<(u8, Flags)>;
+
+    /// Decode which signal supplies NA0 for the given address control bits.
+    fn decode_address_control(address_control: u8, na0: u8) -> AddressSource;
+}
+
+/// The standard 2i as taught in the Leipzig hardware course, with the full
+/// 16-entry opcode table.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Standard;
+
+impl Variant for Standard {
+    fn alu_calculate(instruction: u8, a: u8, b: u8, carry: bool) -> Result<(u8, Flags)> {
+        let (result, carry) = match instruction {
+            0b0000 => (a, false),
+            0b0001 => (b, false),
+            0b0010 => (!(a | b), false),
+            0b0011 => (0, false),
+            0b0100 => a.overflowing_add(b),
+            0b0101 => { // inverted carry
+                let tmp1 = a.overflowing_add(b);
+                let tmp2 = tmp1.0.overflowing_add(1);
+                (tmp2.0, !(tmp1.1 | tmp2.1))
+            }
+            0b0110 => {
+                let tmp1 = a.overflowing_add(b);
+                let tmp2 = tmp1.0.overflowing_add(if carry {1} else {0});
+                (tmp2.0, tmp1.1 | tmp2.1)
+            }
+            0b0111 => { // inverted carry
+                let tmp1 = a.overflowing_add(b);
+                let tmp2 = tmp1.0.overflowing_add(if carry {0} else {1});
+                (tmp2.0, !(tmp1.1 | tmp2.1))
+            }
+            0b1000 => (a >> 1, a & 0b00000001 != 0),
+            0b1001 => (a.rotate_right(1), a & 0b00000001 != 0),
+            0b1010 => (a >> 1 | (carry as u8) << 7, a & 0b00000001 != 0),
+            0b1011 => (a >> 1 | (a & 0b10000000), a & 0b00000001 != 0),
+            0b1100 => (0, false),
+            0b1101 => (0, true),
+            0b1110 => (0, carry),
+            0b1111 => (0, !carry),
+            _ => panic!("Invalid alu instruction {}", instruction),
+        };
+
+        let negative = result & 0b10000000 != 0; // two's complement
+        let zero = result == 0;
+
+        Ok((result, Flags::new(carry, negative, zero)))
+    }
+
+    fn decode_address_control(address_control: u8, na0: u8) -> AddressSource {
+        match address_control << 1 | na0 {
+            0b000 | 0b001 => AddressSource::Direct,
+            0b010 => AddressSource::VolatileInterrupt,
+            0b011 => AddressSource::StoredCarry,
+            0b100 => AddressSource::Carry,
+            0b101 => AddressSource::Zero,
+            0b110 => AddressSource::Negative,
+            0b111 => AddressSource::StoredInterrupt,
+            _ => panic!("Invalid address control"),
+        }
+    }
+}
+
+/// A reduced revision of the 2i lab that is missing the algebraic
+/// (arithmetic) right shift opcode (`0b1011`). Address-control decoding is
+/// unchanged from `Standard`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Reduced;
+
+impl Variant for Reduced {
+    fn alu_calculate(instruction: u8, a: u8, b: u8, carry: bool) -> Result<(u8, Flags)> {
+        if instruction == 0b1011 {
+            return Err(Error::Cpu("Alu instruction not implemented by this variant"));
+        }
+
+        Standard::alu_calculate(instruction, a, b, carry)
+    }
+
+    fn decode_address_control(address_control: u8, na0: u8) -> AddressSource {
+        Standard::decode_address_control(address_control, na0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduced_faults_cleanly_on_algebraic_shift() {
+        match Reduced::alu_calculate(0b1011, 0b10000000, 0, false) {
+            Err(Error::Cpu(_)) => (),
+            other => panic!("expected an Error::Cpu, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reduced_falls_through_to_standard_for_other_opcodes() {
+        assert_eq!(Reduced::alu_calculate(0b0100, 40, 2, false).unwrap(),
+                   Standard::alu_calculate(0b0100, 40, 2, false).unwrap());
+    }
+}
@@ -1,7 +1,7 @@
 mod emulator;
 
 fn main() {
-    println!("12 + 33 = {}", emulator::alu::Alu::calculate(4, 12, 33, false).0);
+    println!("12 + 33 = {}", emulator::alu::Alu::calculate::<emulator::variant::Standard>(4, 12, 33, false).unwrap().0);
 
     let inst = emulator::instruction::Instruction::new(0b1000001100010100010).unwrap();
     print!("Instruction: {:025b}, ", inst.get_instruction());